@@ -0,0 +1,14 @@
+use tipe::Plotter;
+
+// renders without a window, e.g. on CI with lavapipe:
+// VK_ICD_FILENAMES=/usr/share/vulkan/icd.d/lvp_icd.x86_64.json cargo run --example save_png
+fn main() {
+    let mut plt = Plotter::headless();
+
+    let x : Vec<f32> = (0..100).map(|i| i as f32 / 50. - 1.).collect();
+    let y = x.iter().map(|v| (v * 3.).sin() * 0.8).collect();
+
+    plt.scatter(x, y, 0.02, [1., 1., 1., 1.])
+       .save_png("scatter.png", 800, 600)
+       .expect("failed to save scatter.png");
+}
@@ -0,0 +1,128 @@
+use crate::{lines::{Cap, Segment}, scale::Scale, ticks::Ticks};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+// a formatted major tick, `position` is the outer end of the tick in clip space with y up
+#[derive(Clone, Debug)]
+pub struct TickLabel {
+    pub axis: Axis,
+    pub position: [f32; 2],
+    pub text: String,
+}
+
+// look of the spines and ticks around the data
+#[derive(Clone, Debug)]
+pub struct Axes {
+    pub visible: bool,
+    pub color: [f32; 4],
+    // line width in pixels
+    pub width: f32,
+    // tick lengths in pixels, outside of the box
+    pub major_length: f32,
+    pub minor_length: f32,
+    pub max_ticks: usize,
+    // space left around the box for the labels in pixels: left, right, bottom, top
+    pub padding: [f32; 4],
+    // how the data is spread along x and y
+    pub scales: [Scale; 2],
+}
+
+impl Default for Axes {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            color: [1., 1., 1., 1.],
+            width: 1.,
+            major_length: 6.,
+            minor_length: 3.,
+            max_ticks: 8,
+            padding: [70., 20., 50., 40.],
+            scales: [Scale::Linear; 2],
+        }
+    }
+}
+
+impl Axes {
+    // [[left, right], [bottom, top]] of the box in clip space with y up
+    pub fn frame(&self, extent: [u32; 2]) -> [[f32; 2]; 2] {
+        if !self.visible {
+            return [[-1., 1.], [-1., 1.]];
+        }
+
+        let [width, height] = extent.map(|v| v.max(1) as f32);
+        let [left, right, bottom, top] = self.padding;
+
+        [
+            [-1. + 2. * left / width, 1. - 2. * right / width],
+            [-1. + 2. * bottom / height, 1. - 2. * top / height],
+        ]
+    }
+
+    // limits and ticks are in axis coordinates, the labels give the data values
+    pub fn ticks(&self, limits: [[f32; 2]; 2]) -> [(Ticks, Vec<String>); 2] {
+        [0, 1].map(|i| self.scales[i].locate(limits[i], self.max_ticks))
+    }
+
+    // spines and ticks around the frame, in clip space with y up
+    pub fn segments(&self, limits: [[f32; 2]; 2], frame: [[f32; 2]; 2], extent: [u32; 2]) -> Vec<Segment> {
+        if !self.visible {
+            return vec![];
+        }
+
+        let [[x0, x1], [y0, y1]] = frame;
+        let pixel = extent.map(|v| 2. / v.max(1) as f32);
+        let mut segments = Segment::polyline(
+            &[[x0, y0, 0.], [x1, y0, 0.], [x1, y1, 0.], [x0, y1, 0.], [x0, y0, 0.]],
+            self.width,
+            self.color,
+            Cap::Square,
+        );
+
+        let [(x_ticks, _), (y_ticks, _)] = self.ticks(limits);
+
+        for (values, length) in [(&x_ticks.major, self.major_length), (&x_ticks.minor, self.minor_length)] {
+            for x in values.iter().map(|v| to_frame(*v, limits[0], [x0, x1])) {
+                segments.push(Segment::new([x, y0, 0.], [x, y0 - length * pixel[1], 0.], self.width, self.color));
+            }
+        }
+        for (values, length) in [(&y_ticks.major, self.major_length), (&y_ticks.minor, self.minor_length)] {
+            for y in values.iter().map(|v| to_frame(*v, limits[1], [y0, y1])) {
+                segments.push(Segment::new([x0, y, 0.], [x0 - length * pixel[0], y, 0.], self.width, self.color));
+            }
+        }
+
+        segments
+    }
+
+    pub fn tick_labels(&self, limits: [[f32; 2]; 2], frame: [[f32; 2]; 2], extent: [u32; 2]) -> Vec<TickLabel> {
+        if !self.visible {
+            return vec![];
+        }
+
+        let [[x0, x1], [y0, y1]] = frame;
+        let pixel = extent.map(|v| 2. / v.max(1) as f32);
+        let [(x_ticks, x_texts), (y_ticks, y_texts)] = self.ticks(limits);
+
+        let x_labels = x_ticks.major.iter().zip(x_texts).map(|(v, text)| TickLabel {
+            axis: Axis::X,
+            position: [to_frame(*v, limits[0], [x0, x1]), y0 - self.major_length * pixel[1]],
+            text,
+        });
+        let y_labels = y_ticks.major.iter().zip(y_texts).map(|(v, text)| TickLabel {
+            axis: Axis::Y,
+            position: [x0 - self.major_length * pixel[0], to_frame(*v, limits[1], [y0, y1])],
+            text,
+        });
+
+        x_labels.chain(y_labels).collect()
+    }
+}
+
+// maps a value of the limits onto the frame
+fn to_frame(value: f64, [min, max]: [f32; 2], [a, b]: [f32; 2]) -> f32 {
+    a + (value as f32 - min) / (max - min) * (b - a)
+}
@@ -1,18 +1,34 @@
 use std::f32::consts::PI;
 
-use vulkano::buffer::BufferContents;
-use glam::{Mat4, Vec4, Vec3};
+use vulkano::buffer::{BufferContents, Subbuffer};
+use glam::{Mat4, Vec3};
+
+use crate::plot::PlotId;
+
+// how the plot is looked at
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    // flat, the plot fills its frame whatever the rotation of the camera
+    Orthographic,
+    // seen from the distance where the plot fills the window when facing it, fov in degrees.
+    // clip is the near and far planes, None to keep the whole plot between them whatever its rotation
+    Perspective { fov : f32, clip : Option<[f32; 2]> },
+}
+
+// depth kept by the orthographic projection on each side of the plot
+const ORTHOGRAPHIC_DEPTH: f32 = 10.;
 
+// the plot is a card of the size of the window centered at the origin, the camera turns it then looks at it.
+// the camera does not move with the data so the circle radii stay independent of it
 pub struct Camera {
     position : Vec3,
+    // in degrees around x, y then z
     rotation : Vec3,
-    view_pos : Vec4,
+    // width over height of the window
+    aspect : f32,
+    mode : Projection,
     perspective : Mat4,
     view : Mat4,
-    fov : f32,
-    znear : f32,
-    zfar : f32,
-    updated : bool
 }
 
 fn to_rad(theta : f32) -> f32 {
@@ -20,6 +36,7 @@ fn to_rad(theta : f32) -> f32 {
 }
 
 
+// start of the uniform buffer of every shader
 #[derive(Clone, Debug, BufferContents)]
 #[repr(C)]
 pub struct CameraUniformBuffer {
@@ -27,29 +44,41 @@ pub struct CameraUniformBuffer {
     modelview : [[f32; 4]; 4]
 }
 
+// the camera part of the uniform buffers recorded in a command buffer, rewritten before each submission
+pub(crate) type CameraSlots = Vec<(PlotId, Subbuffer<CameraUniformBuffer>)>;
+
+pub(crate) fn camera_slot<T: BufferContents>(uniform_buffer : &Subbuffer<T>) -> Subbuffer<CameraUniformBuffer> {
+    uniform_buffer.as_bytes()
+        .clone()
+        .slice(0..std::mem::size_of::<CameraUniformBuffer>() as u64)
+        .reinterpret()
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Camera {
     pub fn new() -> Self {
-        let position = Vec3::new(0., 0., 0.);
-        let rotation = position.clone();
-        let view_pos = Vec4::new(0., 0., 0., 1.);
-        let perspective = Mat4::IDENTITY;
-        let view = Mat4::IDENTITY;
-
-        Self {
-            position,
-            rotation,
-            view_pos,
-            perspective,
-            updated : true,
-            fov : 20.,
-            znear : 0.,
-            zfar : 1.,
-            view
-        }
+        let mut camera = Self {
+            position : Vec3::ZERO,
+            rotation : Vec3::ZERO,
+            aspect : 1.,
+            mode : Projection::Orthographic,
+            perspective : Mat4::IDENTITY,
+            view : Mat4::IDENTITY,
+        };
+        camera.update_projection_matrix();
+        camera.update_view_matrix();
+        camera
     }
 
     pub fn set_position(&mut self, position : Vec3) -> &mut Self {
         self.position = position;
+        // the fitted clip planes follow the distance to the plot
+        self.update_projection_matrix();
         self.update_view_matrix();
         self
     }
@@ -60,59 +89,103 @@ impl Camera {
         self
     }
 
+    pub fn set_orthographic(&mut self) -> &mut Self {
+        self.set_projection(Projection::Orthographic)
+    }
+
     pub fn set_perspective(&mut self, fov : f32, aspect : f32, znear : f32, zfar : f32) -> &mut Self {
-        let current_matrix = self.perspective;
-        self.fov = fov;
-        self.znear = znear;
-        self.zfar = zfar;
-        self.perspective = Mat4::perspective_rh(to_rad(fov), aspect, znear, zfar);
-
-        if current_matrix != self.perspective {
-            self.updated = true;
-        }
+        assert!(0. < znear && znear < zfar, "the clip planes must satisfy 0 < znear < zfar");
+        self.aspect = aspect;
+        self.set_projection(Projection::Perspective { fov, clip : Some([znear, zfar]) })
+    }
 
+    // perspective with the clip planes fitted to the plot
+    pub fn set_fov(&mut self, fov : f32) -> &mut Self {
+        self.set_projection(Projection::Perspective { fov, clip : None })
+    }
+
+    fn set_projection(&mut self, mode : Projection) -> &mut Self {
+        self.mode = match mode {
+            Projection::Perspective { fov, clip } => Projection::Perspective { fov : fov.clamp(1., 179.), clip },
+            Projection::Orthographic => Projection::Orthographic,
+        };
+        self.update_projection_matrix();
+        self.update_view_matrix();
         self
     }
 
     pub fn update_aspect_ratio(&mut self, aspect : f32) -> &mut Self {
-        let current_matrix = self.perspective;
-        self.perspective = Mat4::perspective_rh(to_rad(self.fov), aspect, self.znear, self.zfar);
-
-        if current_matrix != self.perspective {
-            self.updated = true;
+        if aspect.is_finite() && aspect > 0. && aspect != self.aspect {
+            self.aspect = aspect;
+            self.update_projection_matrix();
+            self.update_view_matrix();
         }
         self
     }
 
-    pub fn get_near_clip(&self) -> f32 { self.znear }
-    pub fn get_far_clip(&self) -> f32 { self.zfar }
+    pub fn mode(&self) -> Projection { self.mode }
+    pub fn get_near_clip(&self) -> f32 { self.clip_planes()[0] }
+    pub fn get_far_clip(&self) -> f32 { self.clip_planes()[1] }
     pub fn get_projection(&self) -> Mat4 { self.perspective }
     pub fn get_view(&self) -> Mat4 { self.view }
     pub fn get_data(&self) -> CameraUniformBuffer {
         CameraUniformBuffer {
             projection : self.perspective.to_cols_array_2d(),
             modelview : self.view.to_cols_array_2d()
+        }
+    }
 
-            // projection : Mat4::IDENTITY.to_cols_array_2d(),
-            // modelview : Mat4::IDENTITY.to_cols_array_2d()
+    // y is flipped back at the end since clip space has y down
+    fn update_projection_matrix(&mut self) {
+        let projection = match self.mode {
+            Projection::Orthographic => Mat4::orthographic_rh(
+                -self.aspect,
+                self.aspect,
+                -1.,
+                1.,
+                -ORTHOGRAPHIC_DEPTH,
+                ORTHOGRAPHIC_DEPTH,
+            ),
+            Projection::Perspective { fov, .. } => {
+                let [znear, zfar] = self.clip_planes();
+                Mat4::perspective_rh(to_rad(fov), self.aspect, znear, zfar)
+            }
+        };
+
+        self.perspective = Mat4::from_scale(Vec3::new(1., -1., 1.)) * projection;
+    }
+
+    // from the camera to the center of the card, far enough for it to fill the height of the view when facing it
+    fn distance(&self) -> f32 {
+        match self.mode {
+            Projection::Orthographic => 0.,
+            Projection::Perspective { fov, .. } => 1. / (to_rad(fov) / 2.).tan(),
+        }
+    }
+
+    // the card reaches at most its half diagonal from its center, the near plane stays in front of the camera
+    fn clip_planes(&self) -> [f32; 2] {
+        match self.mode {
+            Projection::Orthographic => [-ORTHOGRAPHIC_DEPTH, ORTHOGRAPHIC_DEPTH],
+            Projection::Perspective { clip : Some(clip), .. } => clip,
+            Projection::Perspective { clip : None, .. } => {
+                let (depth, reach) = (self.distance() - self.position.z, self.aspect.hypot(1.));
+                let znear = (depth - reach).max(0.01 * self.distance());
+                [znear, (depth + reach).max(2. * znear)]
+            }
         }
     }
 
     pub fn update_view_matrix(&mut self) {
-        // let current_matrix = self.view;
         let mut rot_m = Mat4::IDENTITY;
-        let trans_m = Mat4::from_translation(self.position);
+        let trans_m = Mat4::from_translation(self.position - self.distance() * Vec3::Z);
+        // the clip space of the plot, y down, onto a card with y up and the proportions of the window
+        let card_m = Mat4::from_scale(Vec3::new(self.aspect, -1., 1.));
 
-        rot_m = rot_m * Mat4::from_axis_angle(Vec3::X, to_rad(self.rotation.x));
-        rot_m = rot_m * Mat4::from_axis_angle(Vec3::Y, to_rad(self.rotation.y));
-        rot_m = rot_m * Mat4::from_axis_angle(Vec3::Z, to_rad(self.rotation.z));
+        rot_m *= Mat4::from_axis_angle(Vec3::X, to_rad(self.rotation.x));
+        rot_m *= Mat4::from_axis_angle(Vec3::Y, to_rad(self.rotation.y));
+        rot_m *= Mat4::from_axis_angle(Vec3::Z, to_rad(self.rotation.z));
 
-        self.view = trans_m * rot_m;
-        self.updated = true;
+        self.view = trans_m * rot_m * card_m;
     }
-
-    // fn update(&mut self, delta : f32) {
-    //     self.update = false;
-    // }
 }
-
@@ -1,21 +1,18 @@
-use std::{collections::HashMap, sync::Arc, usize};
+use std::{collections::HashMap, sync::Arc};
 
-use glam::Mat4;
-use vulkano::{buffer::{allocator::{SubbufferAllocator, SubbufferAllocatorCreateInfo}, Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer}, command_buffer::{allocator::CommandBufferAllocator, AutoCommandBufferBuilder, PrimaryAutoCommandBuffer}, descriptor_set::{allocator::{StandardDescriptorSetAlloc, StandardDescriptorSetAllocator}, PersistentDescriptorSet, WriteDescriptorSet}, device::Device, memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator}, pipeline::{graphics::{color_blend::{ColorBlendAttachmentState, ColorBlendState}, input_assembly::InputAssemblyState, multisample::MultisampleState, rasterization::RasterizationState, vertex_input::{Vertex, VertexDefinition}, viewport::{Viewport, ViewportState}, GraphicsPipelineCreateInfo}, layout::PipelineDescriptorSetLayoutCreateInfo, GraphicsPipeline, PipelineBindPoint, PipelineLayout, PipelineShaderStageCreateInfo}, render_pass::{RenderPass, Subpass}, shader::ShaderModule};
-use vulkano::pipeline::{Pipeline};
-use winit::window::WindowId;
-
-use crate::{circles::{self, vs, Circle, MyVertex}, plot::Plot};
+use vulkano::{buffer::{allocator::{SubbufferAllocator, SubbufferAllocatorCreateInfo}, BufferUsage, Subbuffer}, command_buffer::{allocator::CommandBufferAllocator, AutoCommandBufferBuilder, PrimaryAutoCommandBuffer}, descriptor_set::allocator::StandardDescriptorSetAllocator, device::Device, memory::allocator::{MemoryTypeFilter, StandardMemoryAllocator}, pipeline::{graphics::vertex_input::Vertex, GraphicsPipeline}, render_pass::RenderPass, shader::ShaderModule};
+use crate::{camera::CameraSlots, circles::{self, vs, Circle, MyVertex}, instance_buffer::{Changes, InstanceBuffer}, manager::{create_descriptorset, create_instance_buffer, create_pipeline, create_uniform_buffer, create_vertex_buffer, draw_instances, layer_instances}, plot::{Layer, Overlay, Plot, PlotId}};
 
 pub struct CircleManadger {
     device: Arc<Device>,
     memory_allocator: Arc<StandardMemoryAllocator>,
     vs: Arc<ShaderModule>,
     fs: Arc<ShaderModule>,
-    vertex_buffer: Option<Subbuffer<[MyVertex]>>,
-    instance_size: HashMap<WindowId, usize>,
-    instance_offset: HashMap<WindowId, usize>,
-    instance_buffer: Option<Subbuffer<[Circle]>>,
+    vertex_buffer: Subbuffer<[MyVertex]>,
+    // data markers of each plot, updated in place. radii are relative to the height, edges are in pixels
+    instances: InstanceBuffer<Circle>,
+    // legend swatches of each plot, rebuilt when its limits change
+    overlays: HashMap<PlotId, Subbuffer<[Circle]>>,
     pipeline: Option<Arc<GraphicsPipeline>>,
     descriptor_set_allocator: StandardDescriptorSetAllocator,
     // descriptor_set : HashMap<WindowId, Vec<Arc<PersistentDescriptorSet>>,
@@ -35,14 +32,13 @@ impl CircleManadger {
 
         Self {
             device,
-            memory_allocator,
             vs,
             fs,
             pipeline: None,
-            vertex_buffer: None,
-            instance_buffer: None,
-            instance_size: HashMap::new(),
-            instance_offset: HashMap::new(),
+            vertex_buffer: create_vertex_buffer(memory_allocator.clone()),
+            instances: InstanceBuffer::new(memory_allocator.clone()),
+            memory_allocator,
+            overlays: HashMap::new(),
             descriptor_set_allocator,
             subbuffer_allocator
             // descriptor_set: HashMap::new(),
@@ -50,191 +46,80 @@ impl CircleManadger {
     }
 
     pub fn clear_buffer(&mut self) {
-        self.vertex_buffer = None;
-        self.instance_buffer = None;
+        self.instances = InstanceBuffer::new(self.memory_allocator.clone());
+        self.overlays.clear();
     }
 
     pub fn clear(&mut self) {
         self.clear_buffer();
-        self.instance_offset.clear();
         // self.circles = vec![];
     }
 
-    pub fn create_buffers<'a, I>(&mut self, plots : I) -> &mut Self
+    pub fn create_buffers<'a, I>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        plots : I,
+        changes: &mut Changes,
+        ) -> &mut Self
     where
-        I: IntoIterator<Item = &'a Plot>,
-        I::IntoIter: ExactSizeIterator,
+        I: IntoIterator<Item = &'a Plot> + Clone,
     {
-        let mut circles = vec![];
-        let mut offset = 0;
+        let ids: Vec<PlotId> = plots.clone().into_iter().map(|plot| plot.id()).collect();
+        self.instances.retain(&ids);
+        self.overlays.retain(|id, _| ids.contains(id));
         for plot in plots {
-            self.instance_offset.insert(plot.id(), offset);
-            offset += plot.circles.len();
-            self.instance_size.insert(plot.id(), plot.circles.len());
-            circles.append(&mut plot.circles.clone());
+            self.instances.sync(builder, plot.id(), plot.generation(), plot.circles.len(), |start| plot.scaled_circles(start), changes);
         }
 
-        let vertex_buffer = Buffer::from_iter(
-            self.memory_allocator.clone(),
-            BufferCreateInfo {
-                usage: BufferUsage::VERTEX_BUFFER,
-                ..Default::default()
-            },
-            AllocationCreateInfo {
-                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
-                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
-                ..Default::default()
-            },
-            Circle::vertex(),
-        )
-        .unwrap();
+        self
+    }
 
-        let instance_buffer = Buffer::from_iter(
-            self.memory_allocator.clone(),
-            BufferCreateInfo {
-                usage: BufferUsage::VERTEX_BUFFER,
-                ..Default::default()
-            },
-            AllocationCreateInfo {
-                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
-                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
-                ..Default::default()
-            },
-            circles,
-        )
-        .unwrap();
+    pub fn create_overlay_buffers(&mut self, overlays: &[(PlotId, Overlay)]) -> &mut Self {
+        for (id, overlay) in overlays {
+            match create_instance_buffer(self.memory_allocator.clone(), overlay.circles.clone()) {
+                Some(buffer) => self.overlays.insert(*id, buffer),
+                None => self.overlays.remove(id),
+            };
+        }
 
-        self.vertex_buffer = Some(vertex_buffer);
-        self.instance_buffer = Some(instance_buffer);
         self
     }
 
     pub fn build_pipeline(
         &mut self,
         render_pass: Arc<RenderPass>,
-        viewport: Viewport,
     ) -> &mut Self {
-        let vs = self.vs.entry_point("main").unwrap();
-        let fs = self.fs.entry_point("main").unwrap();
-
-        let vertex_input_state = [MyVertex::per_vertex(), Circle::per_instance()]
-            .definition(&vs.info().input_interface)
-            .unwrap();
-
-        let stages = [
-            PipelineShaderStageCreateInfo::new(vs),
-            PipelineShaderStageCreateInfo::new(fs),
-        ];
-
-        let layout = PipelineLayout::new(
+        // the edges are anti-aliased through the alpha channel
+        self.pipeline = Some(create_pipeline(
             self.device.clone(),
-            PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
-                .into_pipeline_layout_create_info(self.device.clone())
-                .unwrap(),
-        )
-        .unwrap();
-
-        let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
-
-        let pipeline = GraphicsPipeline::new(
-            self.device.clone(),
-            None,
-            GraphicsPipelineCreateInfo {
-                stages: stages.into_iter().collect(),
-                vertex_input_state: Some(vertex_input_state),
-                input_assembly_state: Some(InputAssemblyState::default()),
-                viewport_state: Some(ViewportState {
-                    viewports: [viewport].into_iter().collect(),
-                    ..Default::default()
-                }),
-                rasterization_state: Some(RasterizationState::default()),
-                multisample_state: Some(MultisampleState::default()),
-                color_blend_state: Some(ColorBlendState::with_attachment_states(
-                        subpass.num_color_attachments(),
-                        ColorBlendAttachmentState::default(),
-                        )),
-                        subpass: Some(subpass.into()),
-                        ..GraphicsPipelineCreateInfo::layout(layout)
-            },
-            ).unwrap();
-
-        self.pipeline = Some(pipeline);
+            &self.vs,
+            &self.fs,
+            &[MyVertex::per_vertex(), Circle::per_instance()],
+            render_pass,
+            ));
         self
     }
 
-
-
     pub fn draw<A>(
         &mut self,
         builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer<A>, A>,
-        plot: &Plot
+        plot: &Plot,
+        layer: Layer,
+        cameras: &mut CameraSlots,
     ) where
         A: CommandBufferAllocator,
     {
+        let Some(instances) = layer_instances(&self.instances, &self.overlays, plot.id(), layer) else { return };
         let pipeline = self.pipeline.clone().unwrap();
 
-        let uniform_buffer = {
-            let buffer = self.subbuffer_allocator
-                .allocate_sized()
-                .unwrap();
-
-            let uniform_data = vs::UBO {
-                // projection: plot.camera.get_projection().to_cols_array_2d(),
-                // modelview: plot.camera.get_view().to_cols_array_2d(),
-                projection: Mat4::IDENTITY.to_cols_array_2d(),
-                modelview: Mat4::IDENTITY.to_cols_array_2d(),
-            };
-
-            *buffer.write().unwrap() = uniform_data;
-            buffer
-        };
+        let uniform_buffer = create_uniform_buffer(&self.subbuffer_allocator, plot.id(), cameras, vs::UBO {
+            projection: plot.camera.get_projection().to_cols_array_2d(),
+            modelview: plot.camera.get_view().to_cols_array_2d(),
+            data: plot.transform(layer).to_cols_array_2d(),
+            viewport: plot.extent().map(|v| v as f32),
+        });
 
         let descriptor_set = create_descriptorset(pipeline.clone(), &self.descriptor_set_allocator, uniform_buffer);
-
-        builder
-            .bind_pipeline_graphics(pipeline)
-            .unwrap()
-            .bind_descriptor_sets(
-                PipelineBindPoint::Graphics,
-                self.pipeline.clone().unwrap().layout().clone(),
-                0,
-                descriptor_set)
-            .unwrap()
-            .bind_vertex_buffers(0, self.vertex_buffer.clone().unwrap())
-            .unwrap()
-            .bind_vertex_buffers(1, self.instance_buffer.clone().unwrap())
-            .unwrap()
-            .draw(
-                self.vertex_buffer.clone().unwrap().len() as u32,
-                *self.instance_size.get(&plot.id()).unwrap() as u32,
-                0,
-                *self.instance_offset.get(&plot.id()).unwrap() as u32,
-            )
-            .unwrap();
+        draw_instances(builder, pipeline, descriptor_set, self.vertex_buffer.clone(), instances);
     }
 }
-
-
-
-
-fn create_descriptorset(
-    pipeline: Arc<GraphicsPipeline>,
-    descriptor_set_allocator : &StandardDescriptorSetAllocator,
-    buffer: Subbuffer<impl ?Sized>) -> Arc<PersistentDescriptorSet<StandardDescriptorSetAlloc>>
-{
-    let pipeline_layout = pipeline.layout();
-    let descriptor_set_layouts = pipeline_layout.set_layouts();
-
-    let descriptor_set_layout_index = 0;
-    let descriptor_set_layout = descriptor_set_layouts
-        .get(descriptor_set_layout_index)
-        .unwrap();
-
-    PersistentDescriptorSet::new(
-        descriptor_set_allocator,
-        descriptor_set_layout.clone(),
-        [WriteDescriptorSet::buffer(0, buffer)], // 0 is the binding
-        [],
-        )
-        .unwrap()
-}
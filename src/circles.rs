@@ -1,5 +1,7 @@
 use vulkano::{buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer}, command_buffer::{allocator::CommandBufferAllocator, AutoCommandBufferBuilder, PrimaryAutoCommandBuffer}, descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet}, device::Device, memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator}, pipeline::{graphics::{color_blend::{ColorBlendAttachmentState, ColorBlendState}, input_assembly::InputAssemblyState, multisample::MultisampleState, rasterization::RasterizationState, vertex_input::{Vertex, VertexDefinition}, viewport::{Viewport, ViewportState}, GraphicsPipelineCreateInfo}, layout::PipelineDescriptorSetLayoutCreateInfo, GraphicsPipeline, PipelineBindPoint, PipelineLayout, PipelineShaderStageCreateInfo}, render_pass::{RenderPass, Subpass}, shader::ShaderModule};
 
+use crate::markers::Marker;
+
 #[derive(BufferContents, Vertex, Clone, Debug)]
 #[repr(C)]
 pub struct Circle {
@@ -7,8 +9,17 @@ pub struct Circle {
     pub circle_position: [f32; 3],
     #[format(R32G32B32A32_SFLOAT)]
     pub color: [f32; 4],
+    // in clip space units of the viewport height, so the discs stay round
     #[format(R32_SFLOAT)]
     pub radius: f32,
+    #[format(R32G32B32A32_SFLOAT)]
+    pub outline_color: [f32; 4],
+    // in pixels, inside the radius, no outline when 0
+    #[format(R32_SFLOAT)]
+    pub outline_width: f32,
+    // shape drawn inside the radius, see `Marker`
+    #[format(R32_UINT)]
+    pub marker: u32,
 }
 
 #[derive(BufferContents, Vertex, Clone, Debug)]
@@ -30,24 +41,44 @@ pub mod vs {
             layout(location = 1) in vec3 circle_position;
             layout(location = 2) in vec4 color;
             layout(location = 3) in float radius;
+            layout(location = 4) in vec4 outline_color;
+            layout(location = 5) in float outline_width;
+            layout(location = 6) in uint marker;
 
             // uniform data
             layout(set = 0, binding = 0) uniform UBO
             {
                 mat4 projection;
                 mat4 modelview;
+                // data coordinates to [-1; 1]
+                mat4 data;
+                vec2 viewport;
             } ubo;
 
 
             //out
-            layout(location = 0) out vec3 o_position;
+            layout(location = 0) out vec2 o_local;
             layout(location = 1) out vec4 o_color;
+            layout(location = 2) out float o_radius;
+            layout(location = 3) out vec4 o_outline_color;
+            layout(location = 4) out float o_outline_width;
+            layout(location = 5) flat out uint o_marker;
 
             void main() {
-                o_position = local_position;
-                o_color = color;
+                // one more pixel for the anti-aliasing
+                float radius_px = radius * 0.5 * ubo.viewport.y;
+                float extent = radius_px + 1.;
 
-                gl_Position =  ubo.projection * ubo.modelview * vec4(local_position * radius + circle_position, 1.0);
+                o_local = local_position.xy * extent;
+                o_color = color;
+                o_radius = radius_px;
+                o_outline_color = outline_color;
+                o_outline_width = outline_width;
+                o_marker = marker;
+
+                vec4 center = ubo.projection * ubo.modelview * ubo.data * vec4(circle_position, 1.0);
+                center.xy += local_position.xy * extent * 2. / ubo.viewport * center.w;
+                gl_Position = center;
             }
             ",
     }
@@ -59,19 +90,88 @@ pub mod fs {
         src: r"
             #version 460
 
-            layout(location = 0) in vec3 position;
+            layout(location = 0) in vec2 local;
             layout(location = 1) in vec4 color;
+            layout(location = 2) in float radius;
+            layout(location = 3) in vec4 outline_color;
+            layout(location = 4) in float outline_width;
+            layout(location = 5) flat in uint marker;
 
             layout(location = 0) out vec4 f_color;
 
+            const uint SQUARE = 1;
+            const uint TRIANGLE = 2;
+            const uint DIAMOND = 3;
+            const uint CROSS = 4;
+            const uint PLUS = 5;
+            const uint STAR = 6;
+
+            float box(vec2 p, vec2 half_size) {
+                vec2 q = abs(p) - half_size;
+                return length(max(q, 0.)) + min(max(q.x, q.y), 0.);
+            }
+
+            // equilateral, pointing up, centered on its centroid
+            float triangle(vec2 p, float half_side) {
+                const float k = sqrt(3.);
+                p.x = abs(p.x) - half_side;
+                p.y = p.y + half_side / k;
+                if (p.x + k * p.y > 0.)
+                    p = vec2(p.x - k * p.y, -k * p.x - p.y) / 2.;
+                p.x -= clamp(p.x, -2. * half_side, 0.);
+                return -length(p) * sign(p.y);
+            }
+
+            float plus(vec2 p, float r) {
+                float half_thickness = max(0.2 * r, 1.);
+                return min(box(p, vec2(r, half_thickness)), box(p, vec2(half_thickness, r)));
+            }
+
+            // five branches, inner radius rf times the outer one
+            float star(vec2 p, float r, float rf) {
+                const vec2 k1 = vec2(0.809016994375, -0.587785252292);
+                const vec2 k2 = vec2(-k1.x, k1.y);
+                p.x = abs(p.x);
+                p -= 2. * max(dot(k1, p), 0.) * k1;
+                p -= 2. * max(dot(k2, p), 0.) * k2;
+                p.x = abs(p.x);
+                p.y -= r;
+                vec2 ba = rf * vec2(-k1.y, k1.x) - vec2(0., 1.);
+                float h = clamp(dot(p, ba) / dot(ba, ba), 0., r);
+                return length(p - ba * h) * sign(p.y * ba.x - p.x * ba.y);
+            }
+
+            // signed distance to the edge in pixels, negative inside
+            float shape(vec2 p) {
+                // the shapes are defined y up
+                p.y = -p.y;
+                if (marker == SQUARE)
+                    return box(p, vec2(0.85 * radius));
+                if (marker == TRIANGLE)
+                    return triangle(p, 0.866 * radius);
+                if (marker == DIAMOND)
+                    return (abs(p.x) + abs(p.y) - radius) * 0.7071;
+                if (marker == CROSS)
+                    return plus(vec2(p.x + p.y, p.x - p.y) * 0.7071, radius);
+                if (marker == PLUS)
+                    return plus(p, radius);
+                if (marker == STAR)
+                    return star(p, radius, 0.5);
+                return length(p) - radius;
+            }
+
             void main() {
-                // float distance = length(position);
-                // float jsp = step(distance, 1.);
-                // if (jsp != 1.)
-                //     discard;
-                //
-                // f_color = vec4(1., 1., 1., 1.) * jsp;
-                f_color = color;
+                float distance = shape(local);
+
+                float alpha = clamp(0.5 - distance, 0., 1.);
+                if (alpha <= 0.)
+                    discard;
+
+                vec4 fill = color;
+                if (outline_width > 0.)
+                    fill = mix(outline_color, color, clamp(-distance - outline_width + 0.5, 0., 1.));
+
+                f_color = vec4(fill.rgb, fill.a * alpha);
             }
         ",
     }
@@ -107,8 +207,22 @@ impl Circle {
             radius,
             color,
             circle_position: position,
+            outline_color: color,
+            outline_width: 0.,
+            marker: 0,
         }
     }
+
+    pub fn with_outline(mut self, color: [f32; 4], width: f32) -> Self {
+        self.outline_color = color;
+        self.outline_width = width;
+        self
+    }
+
+    pub fn with_marker(mut self, marker: Marker) -> Self {
+        self.marker = marker.id();
+        self
+    }
 }
 
 
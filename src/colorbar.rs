@@ -0,0 +1,108 @@
+use crate::{axes::Axes, colormap::{Colormap, LUT_SIZE}, heatmap::Heatmap, lines::{Cap, Segment}, text::{Align, Text}, ticks};
+
+// colour scale drawn on the right of the axes, with ticks and a label
+#[derive(Clone, Debug)]
+pub struct Colorbar {
+    pub colormap: Colormap,
+    // [vmin, vmax]
+    pub range: [f32; 2],
+    pub label: Option<String>,
+    // in pixels
+    pub width: f32,
+    pub gap: f32,
+}
+
+impl Colorbar {
+    pub fn new(colormap: Colormap, range: [f32; 2]) -> Self {
+        Self {
+            colormap,
+            range,
+            label: None,
+            width: 15.,
+            gap: 20.,
+        }
+    }
+
+    pub fn from_heatmap(heatmap: &Heatmap) -> Self {
+        Self::new(heatmap.colormap.clone(), heatmap.value_range())
+    }
+
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    // pixels taken on the right of the axes, bar, tick labels and label
+    pub(crate) fn space(&self) -> f32 {
+        self.gap + self.width + 60. + if self.label.is_some() { 20. } else { 0. }
+    }
+
+    // [[left, right], [bottom, top]] of the bar next to the frame, in clip space with y up
+    fn rect(&self, frame: [[f32; 2]; 2], pixel: [f32; 2]) -> [[f32; 2]; 2] {
+        let left = frame[0][1] + self.gap * pixel[0];
+        [[left, left + self.width * pixel[0]], frame[1]]
+    }
+
+    // the gradient, a single column from vmin at the bottom to vmax at the top
+    pub(crate) fn heatmap(&self, frame: [[f32; 2]; 2], pixel: [f32; 2]) -> Heatmap {
+        let [vmin, vmax] = self.range;
+        let values = (0..LUT_SIZE)
+            .map(|i| vmin + (vmax - vmin) * (i as f32 + 0.5) / LUT_SIZE as f32)
+            .collect();
+        let [x, y] = self.rect(frame, pixel);
+
+        Heatmap::new(values, [LUT_SIZE, 1])
+            .extent(x, y)
+            .colormap(self.colormap.clone())
+            .range(vmin, vmax)
+    }
+
+    fn tick_positions(&self, y: [f32; 2], axes: &Axes) -> Vec<(f32, String)> {
+        let [vmin, vmax] = self.range;
+        let ticks = ticks::locate(vmin as f64, vmax as f64, axes.max_ticks);
+
+        ticks.major.iter()
+            .zip(ticks.labels())
+            .map(|(v, label)| (y[0] + (*v as f32 - vmin) / (vmax - vmin) * (y[1] - y[0]), label))
+            .collect()
+    }
+
+    pub(crate) fn segments(&self, frame: [[f32; 2]; 2], pixel: [f32; 2], axes: &Axes) -> Vec<Segment> {
+        let [[x0, x1], [y0, y1]] = self.rect(frame, pixel);
+        let mut segments = Segment::polyline(
+            &[[x0, y0, 0.], [x1, y0, 0.], [x1, y1, 0.], [x0, y1, 0.], [x0, y0, 0.]],
+            axes.width,
+            axes.color,
+            Cap::Square,
+        );
+
+        for (y, _) in self.tick_positions([y0, y1], axes) {
+            segments.push(Segment::new([x1, y, 0.], [x1 + axes.major_length * pixel[0], y, 0.], axes.width, axes.color));
+        }
+
+        segments
+    }
+
+    pub(crate) fn texts(&self, frame: [[f32; 2]; 2], pixel: [f32; 2], axes: &Axes) -> Vec<Text> {
+        let [[_, x1], [y0, y1]] = self.rect(frame, pixel);
+        let left = x1 + (axes.major_length + 3.) * pixel[0];
+
+        let mut texts: Vec<Text> = self.tick_positions([y0, y1], axes)
+            .into_iter()
+            .map(|(y, label)| Text::new([left, y], label)
+                .align(Align::Start, Align::Center)
+                .size(12.)
+                .color(axes.color))
+            .collect();
+
+        // against the right border of the window, reading upwards
+        if let Some(label) = &self.label {
+            texts.push(Text::new([1. - 3. * pixel[0], 0.5 * (y0 + y1)], label.clone())
+                .align(Align::Center, Align::Start)
+                .rotation(std::f32::consts::FRAC_PI_2)
+                .color(axes.color));
+        }
+
+        texts
+    }
+}
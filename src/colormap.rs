@@ -0,0 +1,179 @@
+use crate::colormap_data::{COOLWARM, INFERNO, MAGMA, PLASMA, VIRIDIS};
+
+// the maps without a full table, sampled at a few evenly spaced points and interpolated linearly
+const CIVIDIS: [[f32; 3]; 5] = [
+    [0.000000, 0.135112, 0.304751],
+    [0.208030, 0.271603, 0.423941],
+    [0.485210, 0.482840, 0.471439],
+    [0.737683, 0.684529, 0.434990],
+    [0.995737, 0.909344, 0.217772],
+];
+
+const GREY: [[f32; 3]; 2] = [[0., 0., 0.], [1., 1., 1.]];
+
+// cyclic, both ends are the same colour
+const TWILIGHT: [[f32; 3]; 5] = [
+    [0.885750, 0.850009, 0.887974],
+    [0.369214, 0.496063, 0.726592],
+    [0.185001, 0.078128, 0.232004],
+    [0.694000, 0.349900, 0.241100],
+    [0.885750, 0.850009, 0.887974],
+];
+
+// colour of the values that can not be mapped
+pub const BAD_COLOR: [f32; 4] = [0., 0., 0., 0.];
+
+// number of entries of the lookup textures
+pub const LUT_SIZE: usize = 256;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Colormap {
+    #[default]
+    Viridis,
+    Magma,
+    Inferno,
+    Plasma,
+    Cividis,
+    Grey,
+    Coolwarm,
+    Twilight,
+    // (position in [0; 1], colour) sorted by position, see `Colormap::custom`
+    Custom(Vec<(f32, [f32; 4])>),
+}
+
+impl Colormap {
+    // control points can be given in any order, positions are clamped to [0; 1]
+    pub fn custom(mut points: Vec<(f32, [f32; 4])>) -> Self {
+        assert!(!points.is_empty(), "a colormap needs at least one colour");
+        for point in &mut points {
+            point.0 = point.0.clamp(0., 1.);
+        }
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        Colormap::Custom(points)
+    }
+
+    // evenly spaced colours
+    pub fn from_colors(colors: Vec<[f32; 4]>) -> Self {
+        let n = (colors.len().max(2) - 1) as f32;
+        Self::custom(colors.into_iter().enumerate().map(|(i, c)| (i as f32 / n, c)).collect())
+    }
+
+    fn samples(&self) -> &'static [[f32; 3]] {
+        match self {
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Magma => &MAGMA,
+            Colormap::Inferno => &INFERNO,
+            Colormap::Plasma => &PLASMA,
+            Colormap::Cividis => &CIVIDIS,
+            Colormap::Grey => &GREY,
+            Colormap::Coolwarm => &COOLWARM,
+            Colormap::Twilight => &TWILIGHT,
+            Colormap::Custom(_) => &[],
+        }
+    }
+
+    // t is clamped to [0; 1]
+    pub fn color(&self, t: f32) -> [f32; 4] {
+        if t.is_nan() {
+            return BAD_COLOR;
+        }
+        let t = t.clamp(0., 1.);
+
+        if let Colormap::Custom(points) = self {
+            let i = points.partition_point(|p| p.0 <= t);
+            return match (i.checked_sub(1).map(|i| points[i]), points.get(i)) {
+                (Some((ta, a)), Some((tb, b))) => mix(a, *b, (t - ta) / (tb - ta)),
+                (Some((_, a)), None) => a,
+                (None, Some((_, b))) => *b,
+                (None, None) => BAD_COLOR,
+            };
+        }
+
+        let samples = self.samples();
+        let x = t * (samples.len() - 1) as f32;
+        let i = (x.floor() as usize).min(samples.len() - 2);
+        let [a, b] = [samples[i], samples[i + 1]].map(|[r, g, b]| [r, g, b, 1.]);
+
+        mix(a, b, x - i as f32)
+    }
+
+    // maps [vmin; vmax] onto the colormap, the range of the finite values when None
+    pub fn map(&self, values: &[f32], range: Option<[f32; 2]>) -> Vec<[f32; 4]> {
+        let [vmin, vmax] = range.unwrap_or_else(|| value_range(values));
+        let scale = if vmax != vmin { 1. / (vmax - vmin) } else { 0. };
+
+        values.iter()
+            .map(|v| match v.is_finite() {
+                true => self.color((v - vmin) * scale),
+                false => BAD_COLOR,
+            })
+            .collect()
+    }
+
+    // evenly spaced samples, what is uploaded as a lookup texture
+    pub fn lut(&self, n: usize) -> Vec<[f32; 4]> {
+        let last = (n.max(2) - 1) as f32;
+        (0..n).map(|i| self.color(i as f32 / last)).collect()
+    }
+
+    pub(crate) fn lut_rgba8(&self) -> Vec<u8> {
+        self.lut(LUT_SIZE)
+            .into_iter()
+            .flatten()
+            .map(|c| (c.clamp(0., 1.) * 255.).round() as u8)
+            .collect()
+    }
+}
+
+fn mix(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    [0, 1, 2, 3].map(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+// [min, max] of the finite values, [0, 1] without any
+pub fn value_range(values: &[f32]) -> [f32; 2] {
+    values.iter()
+        .filter(|v| v.is_finite())
+        .fold(None, |range: Option<[f32; 2]>, v| match range {
+            None => Some([*v, *v]),
+            Some([min, max]) => Some([min.min(*v), max.max(*v)]),
+        })
+        .unwrap_or([0., 1.])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: [f32; 4], b: [f32; 4]) {
+        assert!(a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5), "{a:?} {b:?}");
+    }
+
+    #[test]
+    fn tables_are_sampled_exactly() {
+        let [r, g, b] = VIRIDIS[128];
+        assert_close(Colormap::Viridis.color(128. / 255.), [r, g, b, 1.]);
+        assert_close(Colormap::Magma.color(0.), [0.001462, 0.000466, 0.013866, 1.]);
+        assert_close(Colormap::Inferno.color(2.), [0.988362, 0.998364, 0.644924, 1.]);
+
+        // halfway between two entries
+        let ([r0, g0, b0], [r1, g1, b1]) = (PLASMA[10], PLASMA[11]);
+        assert_close(Colormap::Plasma.color(10.5 / 255.), [(r0 + r1) / 2., (g0 + g1) / 2., (b0 + b1) / 2., 1.]);
+    }
+
+    #[test]
+    fn lut_covers_the_table() {
+        let lut = Colormap::Coolwarm.lut(LUT_SIZE);
+        for (color, [r, g, b]) in lut.iter().zip(COOLWARM) {
+            assert_close(*color, [r, g, b, 1.]);
+        }
+    }
+
+    #[test]
+    fn custom_and_bad_values() {
+        let map = Colormap::custom(vec![(1., [1., 1., 1., 1.]), (0., [0., 0., 0., 0.5])]);
+        assert_close(map.color(0.25), [0.25, 0.25, 0.25, 0.625]);
+        assert_eq!(map.color(f32::NAN), BAD_COLOR);
+        assert_eq!(map.map(&[f32::INFINITY, 0., 2.], None), vec![BAD_COLOR, [0., 0., 0., 0.5], [1.; 4]]);
+    }
+}
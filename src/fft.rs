@@ -56,3 +56,29 @@ pub fn fftfreq(n : usize, d : f64) -> Vec<f64> {
      )
      .collect()
 }
+
+pub fn hann(n : usize) -> Vec<f64> {
+    (0..n).map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / n as f64).cos())
+          .collect()
+}
+
+//short time fourier transform, one hann windowed spectrum of `window` values every `hop` values.
+//window must be a power of two.
+pub fn stft(values: &[f64], window : usize, hop : usize) -> Vec<Vec<Complex<f64>>> {
+    assert!(window.is_power_of_two(), "stft window must be a power of two");
+    assert!(hop > 0, "stft hop must be positive");
+
+    let weights = hann(window);
+    (0..values.len().saturating_sub(window) + 1)
+        .step_by(hop)
+        .filter(|start| start + window <= values.len())
+        .map(|start| {
+            let frame = values[start..start + window]
+                .iter()
+                .zip(weights.iter())
+                .map(|(v, w)| Complex::new(v * w, 0.))
+                .collect();
+            my_fft(&frame)
+        })
+        .collect()
+}
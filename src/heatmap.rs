@@ -0,0 +1,118 @@
+use crate::colormap::{self, Colormap};
+
+// grid of values coloured on the gpu through a colormap lookup texture
+#[derive(Clone, Debug)]
+pub struct Heatmap {
+    // row major, the first row at the bottom
+    pub values: Vec<f32>,
+    // rows, columns
+    pub shape: [usize; 2],
+    // [[x0, x1], [y0, y1]] covered by the grid
+    pub extent: [[f32; 2]; 2],
+    pub colormap: Colormap,
+    // [vmin, vmax], the range of the finite values when None
+    pub range: Option<[f32; 2]>,
+}
+
+pub mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec3 local_position;
+
+            // uniform data
+            layout(set = 0, binding = 0) uniform UBO
+            {
+                mat4 projection;
+                mat4 modelview;
+                // data coordinates to [-1; 1]
+                mat4 data;
+                // lower left corner and size
+                vec4 rect;
+                vec2 range;
+            } ubo;
+
+            //out
+            layout(location = 0) out vec2 o_uv;
+
+            void main() {
+                vec2 t = (local_position.xy + 1.) * 0.5;
+                o_uv = t;
+
+                vec2 position = ubo.rect.xy + t * ubo.rect.zw;
+                gl_Position = ubo.projection * ubo.modelview * ubo.data * vec4(position, 0., 1.0);
+            }
+            ",
+    }
+}
+
+pub mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec2 uv;
+
+            layout(set = 0, binding = 0) uniform UBO
+            {
+                mat4 projection;
+                mat4 modelview;
+                mat4 data;
+                vec4 rect;
+                vec2 range;
+            } ubo;
+            layout(set = 0, binding = 1) uniform sampler2D values;
+            layout(set = 0, binding = 2) uniform sampler2D lut;
+
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                float value = texture(values, uv).r;
+                if (isnan(value) || isinf(value))
+                    discard;
+
+                float t = ubo.range.y != ubo.range.x ? (value - ubo.range.x) / (ubo.range.y - ubo.range.x) : 0.;
+                // through the centers of the first and last texels
+                float n = float(textureSize(lut, 0).x);
+                f_color = texture(lut, vec2((clamp(t, 0., 1.) * (n - 1.) + 0.5) / n, 0.5));
+            }
+        ",
+    }
+}
+
+impl Heatmap {
+    // one cell per unit from the origin until `extent` is called
+    pub fn new(values: Vec<f32>, shape: [usize; 2]) -> Self {
+        assert_eq!(values.len(), shape[0] * shape[1], "the values do not match the shape");
+
+        Self {
+            values,
+            shape,
+            extent: [[0., shape[1] as f32], [0., shape[0] as f32]],
+            colormap: Colormap::default(),
+            range: None,
+        }
+    }
+
+    pub fn extent(mut self, x: [f32; 2], y: [f32; 2]) -> Self {
+        self.extent = [x, y];
+        self
+    }
+
+    pub fn colormap(mut self, colormap: Colormap) -> Self {
+        self.colormap = colormap;
+        self
+    }
+
+    pub fn range(mut self, vmin: f32, vmax: f32) -> Self {
+        self.range = Some([vmin, vmax]);
+        self
+    }
+
+    pub fn value_range(&self) -> [f32; 2] {
+        self.range.unwrap_or_else(|| colormap::value_range(&self.values))
+    }
+}
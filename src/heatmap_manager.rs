@@ -0,0 +1,284 @@
+use std::{collections::HashMap, error::Error, sync::Arc};
+
+use vulkano::{buffer::{allocator::{SubbufferAllocator, SubbufferAllocatorCreateInfo}, BufferUsage, Subbuffer}, command_buffer::{allocator::CommandBufferAllocator, AutoCommandBufferBuilder, PrimaryAutoCommandBuffer}, descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet}, device::Device, format::Format, image::{sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo}, view::ImageView}, memory::allocator::{MemoryTypeFilter, StandardMemoryAllocator}, pipeline::{graphics::vertex_input::Vertex, GraphicsPipeline, Pipeline, PipelineBindPoint}, render_pass::RenderPass, shader::ShaderModule};
+
+use crate::{camera::CameraSlots, manager::{create_uniform_buffer, create_pipeline, create_vertex_buffer}, circles::MyVertex, colormap::{Colormap, LUT_SIZE}, heatmap::{self, vs, Heatmap}, instance_buffer::Changes, plot::{Layer, Overlay, Plot, PlotId}, texture::create_texture};
+
+// part of the values in a texture of at most the size the device supports
+struct Tile {
+    values: Arc<ImageView>,
+    // [[c0, c1], [r0, r1]], the columns and rows covered as fractions of the heatmap ones
+    cells: [[f32; 2]; 2],
+}
+
+// textures of a heatmap once uploaded
+struct GpuHeatmap {
+    tiles: Vec<Tile>,
+    lut: Arc<ImageView>,
+    // [[x0, x1], [y0, y1]]
+    extent: [[f32; 2]; 2],
+    range: [f32; 2],
+}
+
+// what was uploaded for a plot, the data heatmaps are only uploaded again when the plot changes other than by
+// appending to them. the overlay is drawn through the transform of the axes instead of the data one
+#[derive(Default)]
+struct PlotHeatmaps {
+    generation: u64,
+    // number of heatmaps of the plot already uploaded
+    source: usize,
+    data: Vec<GpuHeatmap>,
+    // the colorbar, uploaded again when its colormap or range change and only moved when the plot is resized
+    overlay: Vec<(Colormap, GpuHeatmap)>,
+}
+
+pub struct HeatmapManager {
+    device: Arc<Device>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    vs: Arc<ShaderModule>,
+    fs: Arc<ShaderModule>,
+    vertex_buffer: Subbuffer<[MyVertex]>,
+    heatmaps: HashMap<PlotId, PlotHeatmaps>,
+    pipeline: Option<Arc<GraphicsPipeline>>,
+    // cells stay sharp, colours are interpolated along the colormap
+    value_sampler: Arc<Sampler>,
+    lut_sampler: Arc<Sampler>,
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
+    subbuffer_allocator : SubbufferAllocator
+}
+
+impl HeatmapManager {
+    pub fn new(device: Arc<Device>, memory_allocator: Arc<StandardMemoryAllocator>) -> Self {
+        let vs = heatmap::vs::load(device.clone()).unwrap();
+        let fs = heatmap::fs::load(device.clone()).unwrap();
+        let descriptor_set_allocator = StandardDescriptorSetAllocator::new(device.clone(), Default::default());
+        let subbuffer_allocator = SubbufferAllocator::new(memory_allocator.clone(), SubbufferAllocatorCreateInfo {
+            buffer_usage: BufferUsage::UNIFORM_BUFFER,
+            memory_type_filter: MemoryTypeFilter::HOST_SEQUENTIAL_WRITE | MemoryTypeFilter::PREFER_DEVICE,
+            ..Default::default()
+        });
+
+        let value_sampler = Sampler::new(device.clone(), SamplerCreateInfo {
+            mag_filter: Filter::Nearest,
+            min_filter: Filter::Nearest,
+            address_mode: [SamplerAddressMode::ClampToEdge; 3],
+            ..Default::default()
+        }).unwrap();
+        let lut_sampler = Sampler::new(device.clone(), SamplerCreateInfo {
+            mag_filter: Filter::Linear,
+            min_filter: Filter::Linear,
+            address_mode: [SamplerAddressMode::ClampToEdge; 3],
+            ..Default::default()
+        }).unwrap();
+        let vertex_buffer = create_vertex_buffer(memory_allocator.clone());
+
+        Self {
+            device,
+            memory_allocator,
+            vs,
+            fs,
+            vertex_buffer,
+            heatmaps: HashMap::new(),
+            pipeline: None,
+            value_sampler,
+            lut_sampler,
+            descriptor_set_allocator,
+            subbuffer_allocator
+        }
+    }
+
+    // heatmaps larger than the device limit are split in tiles, each row of a tile copied out of the values
+    fn upload(&self, builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, heatmap: &Heatmap) -> Result<GpuHeatmap, Box<dyn Error>> {
+        let [rows, columns] = heatmap.shape;
+        let size = self.device.physical_device().properties().max_image_dimension2_d as usize;
+
+        let mut tiles = vec![];
+        for r0 in (0..rows).step_by(size) {
+            let r1 = (r0 + size).min(rows);
+            for c0 in (0..columns).step_by(size) {
+                let c1 = (c0 + size).min(columns);
+                let texels: Vec<f32> = (r0..r1)
+                    .flat_map(|r| heatmap.values[r * columns + c0..r * columns + c1].iter().copied())
+                    .collect();
+                let values = create_texture(
+                    self.memory_allocator.clone(),
+                    builder,
+                    Format::R32_SFLOAT,
+                    [(c1 - c0) as u32, (r1 - r0) as u32],
+                    texels,
+                    )?;
+                let cells = [
+                    [c0 as f32 / columns as f32, c1 as f32 / columns as f32],
+                    [r0 as f32 / rows as f32, r1 as f32 / rows as f32],
+                ];
+                tiles.push(Tile { values, cells });
+            }
+        }
+
+        let lut = create_texture(
+            self.memory_allocator.clone(),
+            builder,
+            Format::R8G8B8A8_UNORM,
+            [LUT_SIZE as u32, 1],
+            heatmap.colormap.lut_rgba8(),
+            )?;
+
+        Ok(GpuHeatmap {
+            tiles,
+            lut,
+            extent: heatmap.extent,
+            range: heatmap.value_range(),
+        })
+    }
+
+    pub fn create_buffers<'a, I>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        plots : I,
+        changes: &mut Changes,
+        ) -> &mut Self
+    where
+        I: IntoIterator<Item = &'a Plot> + Clone,
+    {
+        let ids: Vec<PlotId> = plots.clone().into_iter().map(|plot| plot.id()).collect();
+        self.heatmaps.retain(|id, _| ids.contains(id));
+        for plot in plots.clone() {
+            let mut heatmaps = self.heatmaps.remove(&plot.id()).unwrap_or_default();
+            let drawn = heatmaps.data.len();
+            // the extents follow the masked edges
+            let reset = heatmaps.generation != plot.masked_generation();
+            if reset {
+                heatmaps = PlotHeatmaps { generation: plot.masked_generation(), overlay: heatmaps.overlay, ..Default::default() };
+            }
+
+            for heatmap in plot.scaled_heatmaps(heatmaps.source).iter().filter(|h| !h.values.is_empty()) {
+                match self.upload(builder, heatmap) {
+                    Ok(gpu) => heatmaps.data.push(gpu),
+                    Err(e) => println!("failed to upload a heatmap of {:?} values: {e}", heatmap.shape),
+                }
+            }
+            // new textures, the old ones stay alive as long as the command buffers drawing them
+            if (reset && drawn > 0) || heatmaps.data.len() != drawn {
+                changes.moved.insert(plot.id());
+            }
+            heatmaps.source = plot.heatmaps.len();
+
+            self.heatmaps.insert(plot.id(), heatmaps);
+        }
+
+        self
+    }
+
+    // the overlay follows the size of the plot, its textures stay in place unless the colormap or range change
+    pub fn create_overlay_buffers(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        overlays: &[(PlotId, Overlay)],
+        ) -> &mut Self
+    {
+        for (id, overlay) in overlays {
+            let mut heatmaps = self.heatmaps.remove(id).unwrap_or_default();
+
+            let uploaded = overlay.heatmaps.len() == heatmaps.overlay.len()
+                && overlay.heatmaps.iter().zip(&heatmaps.overlay).all(|(h, (colormap, gpu))| h.colormap == *colormap && h.value_range() == gpu.range);
+            if !uploaded {
+                heatmaps.overlay = overlay.heatmaps.iter()
+                    .filter_map(|h| match self.upload(builder, h) {
+                        Ok(gpu) => Some((h.colormap.clone(), gpu)),
+                        Err(e) => { println!("failed to upload the colorbar: {e}"); None },
+                    })
+                    .collect();
+            }
+            for (heatmap, (_, gpu)) in overlay.heatmaps.iter().zip(&mut heatmaps.overlay) {
+                gpu.extent = heatmap.extent;
+            }
+
+            self.heatmaps.insert(*id, heatmaps);
+        }
+
+        self
+    }
+
+    pub fn build_pipeline(
+        &mut self,
+        render_pass: Arc<RenderPass>,
+    ) -> &mut Self {
+        // custom colormaps can be translucent
+        self.pipeline = Some(create_pipeline(
+            self.device.clone(),
+            &self.vs,
+            &self.fs,
+            &[MyVertex::per_vertex()],
+            render_pass,
+            ));
+        self
+    }
+
+    pub fn draw<A>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer<A>, A>,
+        plot: &Plot,
+        layer: Layer,
+        cameras: &mut CameraSlots,
+    ) where
+        A: CommandBufferAllocator,
+    {
+        let heatmaps = match (self.heatmaps.get(&plot.id()), layer) {
+            (Some(heatmaps), Layer::Data) => heatmaps.data.iter().collect::<Vec<_>>(),
+            (Some(heatmaps), Layer::Overlay) => heatmaps.overlay.iter().map(|(_, gpu)| gpu).collect(),
+            (None, _) => return,
+        };
+        if heatmaps.is_empty() {
+            return;
+        }
+        let pipeline = self.pipeline.clone().unwrap();
+        let vertex_buffer = self.vertex_buffer.clone();
+
+        builder
+            .bind_pipeline_graphics(pipeline.clone())
+            .unwrap()
+            .bind_vertex_buffers(0, vertex_buffer.clone())
+            .unwrap();
+
+        let tiles = heatmaps.iter().flat_map(|heatmap| heatmap.tiles.iter().map(move |tile| (heatmap, tile)));
+        for (heatmap, tile) in tiles {
+            let transform = plot.transform(layer);
+
+            let uniform_buffer = create_uniform_buffer(&self.subbuffer_allocator, plot.id(), cameras, vs::UBO {
+                projection: plot.camera.get_projection().to_cols_array_2d(),
+                modelview: plot.camera.get_view().to_cols_array_2d(),
+                data: transform.to_cols_array_2d(),
+                rect: rect(heatmap.extent, tile.cells),
+                range: heatmap.range,
+            });
+
+            let descriptor_set = PersistentDescriptorSet::new(
+                &self.descriptor_set_allocator,
+                pipeline.layout().set_layouts()[0].clone(),
+                [
+                    WriteDescriptorSet::buffer(0, uniform_buffer),
+                    WriteDescriptorSet::image_view_sampler(1, tile.values.clone(), self.value_sampler.clone()),
+                    WriteDescriptorSet::image_view_sampler(2, heatmap.lut.clone(), self.lut_sampler.clone()),
+                ],
+                [],
+                )
+                .unwrap();
+
+            builder
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Graphics,
+                    pipeline.layout().clone(),
+                    0,
+                    descriptor_set)
+                .unwrap()
+                .draw(vertex_buffer.len() as u32, 1, 0, 0)
+                .unwrap();
+        }
+    }
+}
+
+// lower left corner and size of the part of [[x0, x1], [y0, y1]] covered by the cells of a tile
+fn rect([[x0, x1], [y0, y1]]: [[f32; 2]; 2], [[c0, c1], [r0, r1]]: [[f32; 2]; 2]) -> [f32; 4] {
+    let [w, h] = [x1 - x0, y1 - y0];
+    [x0 + c0 * w, y0 + r0 * h, (c1 - c0) * w, (r1 - r0) * h]
+}
@@ -0,0 +1,201 @@
+use std::{collections::{HashMap, HashSet}, sync::Arc};
+
+use vulkano::{buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer}, command_buffer::{AutoCommandBufferBuilder, CopyBufferInfo, PrimaryAutoCommandBuffer}, memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator}};
+
+use crate::plot::PlotId;
+
+// what a rebuild of the buffers did to the plots, see `Renderer::create_buffer`
+#[derive(Default)]
+pub(crate) struct Changes {
+    // instances rewritten where the frames in flight read them
+    pub(crate) overwritten: HashSet<PlotId>,
+    // drawn from another buffer, range or count, their command buffers are recorded again
+    pub(crate) moved: HashSet<PlotId>,
+}
+
+// instances of a plot, in the order of its data
+#[derive(Clone, Copy, Debug)]
+struct Slot {
+    first: u64,
+    len: u64,
+    capacity: u64,
+    // of the data the instances were made from
+    generation: u64,
+    source: usize,
+}
+
+// device local buffer holding the data instances of every plot, each plot owning a range of it.
+// appended data is staged and copied after the range, a range without room is moved to the end,
+// the buffer doubles and is compacted when full
+pub(crate) struct InstanceBuffer<T> {
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    buffer: Option<Subbuffer<[T]>>,
+    // end of the last range
+    end: u64,
+    slots: HashMap<PlotId, Slot>,
+}
+
+// the smallest range given to a plot
+const MIN_CAPACITY: u64 = 64;
+
+impl<T: BufferContents> InstanceBuffer<T> {
+    pub(crate) fn new(memory_allocator: Arc<StandardMemoryAllocator>) -> Self {
+        Self { memory_allocator, buffer: None, end: 0, slots: HashMap::new() }
+    }
+
+    pub(crate) fn buffer(&self) -> Option<Subbuffer<[T]>> {
+        self.buffer.clone()
+    }
+
+    // first instance and count of the plot
+    pub(crate) fn range(&self, plot: PlotId) -> Option<(u64, u64)> {
+        self.slots.get(&plot).map(|slot| (slot.first, slot.len))
+    }
+
+    // forgets the plots that are gone, their ranges are reclaimed by the next compaction
+    pub(crate) fn retain(&mut self, plots: &[PlotId]) {
+        self.slots.retain(|id, _| plots.contains(id));
+    }
+
+    // uploads what changed in the plot since the last call. generation changes when the items do other than
+    // by appending, source is the number of items of the plot, instances turns the items from an index on
+    // into instances. data only appended to is uploaded from where the last upload ended, anything else is
+    // uploaded again. appending writes past the instances drawn, so only uploading again in place overwrites
+    pub(crate) fn sync<F>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        plot: PlotId,
+        generation: u64,
+        source: usize,
+        instances: F,
+        changes: &mut Changes,
+        )
+    where
+        F: FnOnce(usize) -> Vec<T>,
+    {
+        let old = self.slots.get(&plot).copied();
+        let (from, start) = match old {
+            Some(slot) if slot.generation == generation && slot.source == source => return,
+            Some(slot) if slot.generation == generation && slot.source < source => (slot.source, slot.len),
+            _ => (0, 0),
+        };
+
+        let instances = instances(from);
+        let len = start + instances.len() as u64;
+        let slot = self.reserve(builder, plot, start, len, changes);
+        if !instances.is_empty() {
+            self.upload(builder, slot.first + start, instances);
+        }
+
+        if let Some(old) = old {
+            if start == 0 && old.len > 0 && len <= old.capacity {
+                changes.overwritten.insert(plot);
+            }
+        }
+        if old.map(|old| (old.first, old.len)) != Some((slot.first, len)) {
+            changes.moved.insert(plot);
+        }
+        self.slots.insert(plot, Slot { len, generation, source, ..slot });
+    }
+
+    // a slot of the plot with room for len instances, keeping its first `keep` ones
+    fn reserve(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        plot: PlotId,
+        keep: u64,
+        len: u64,
+        changes: &mut Changes,
+        ) -> Slot
+    {
+        match self.slots.get(&plot) {
+            Some(slot) if len <= slot.capacity => return *slot,
+            // nothing to hold yet
+            None if len == 0 => return Slot { first: 0, len: 0, capacity: 0, generation: 0, source: 0 },
+            _ => (),
+        }
+
+        let capacity = (2 * len).max(MIN_CAPACITY);
+        // the old slot is compacted along with the others
+        if self.end + capacity > self.capacity() {
+            self.grow(builder, capacity);
+            changes.moved.extend(self.slots.keys().copied());
+        }
+        let old = self.slots.remove(&plot);
+
+        let slot = Slot { first: self.end, len: keep, capacity, generation: 0, source: 0 };
+        self.end += capacity;
+        if let (Some(old), Some(buffer), true) = (old, &self.buffer, keep > 0) {
+            builder
+                .copy_buffer(CopyBufferInfo::buffers(
+                    buffer.clone().slice(old.first..old.first + keep),
+                    buffer.clone().slice(slot.first..slot.first + keep),
+                ))
+                .unwrap();
+        }
+        slot
+    }
+
+    fn capacity(&self) -> u64 {
+        self.buffer.as_ref().map_or(0, |buffer| buffer.len())
+    }
+
+    // a new buffer with room for `extra` more instances, the ranges of the plots copied packed at its start
+    fn grow(&mut self, builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, extra: u64) {
+        let used: u64 = self.slots.values().map(|slot| slot.capacity).sum();
+        let buffer = Buffer::new_slice::<T>(
+            self.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::VERTEX_BUFFER | BufferUsage::TRANSFER_SRC | BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+            (2 * (used + extra)).max(MIN_CAPACITY),
+        )
+        .unwrap();
+
+        let mut end = 0;
+        for slot in self.slots.values_mut() {
+            if let (Some(old), true) = (&self.buffer, slot.len > 0) {
+                builder
+                    .copy_buffer(CopyBufferInfo::buffers(
+                        old.clone().slice(slot.first..slot.first + slot.len),
+                        buffer.clone().slice(end..end + slot.len),
+                    ))
+                    .unwrap();
+            }
+            slot.first = end;
+            end += slot.capacity;
+        }
+
+        self.buffer = Some(buffer);
+        self.end = end;
+    }
+
+    // copies the instances through a staging buffer, from the given instance on
+    fn upload(&mut self, builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, first: u64, instances: Vec<T>) {
+        let len = instances.len() as u64;
+        let staging = Buffer::from_iter(
+            self.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            instances,
+        )
+        .unwrap();
+
+        let buffer = self.buffer.clone().unwrap();
+        builder
+            .copy_buffer(CopyBufferInfo::buffers(staging, buffer.slice(first..first + len)))
+            .unwrap();
+    }
+}
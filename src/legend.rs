@@ -0,0 +1,216 @@
+use crate::{circles::Circle, lines::{Cap, Segment}, markers::Marker, rectangles::Rectangle, text::{self, Align, Text}};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LegendPosition {
+    // the candidate covering the fewest data points
+    #[default]
+    Best,
+    UpperRight,
+    UpperLeft,
+    LowerLeft,
+    LowerRight,
+    Right,
+    CenterLeft,
+    CenterRight,
+    LowerCenter,
+    UpperCenter,
+    Center,
+}
+
+// tried in this order by `Best`, the first with the fewest points wins
+const CANDIDATES: [LegendPosition; 10] = [
+    LegendPosition::UpperRight,
+    LegendPosition::UpperLeft,
+    LegendPosition::LowerLeft,
+    LegendPosition::LowerRight,
+    LegendPosition::Right,
+    LegendPosition::CenterLeft,
+    LegendPosition::CenterRight,
+    LegendPosition::LowerCenter,
+    LegendPosition::UpperCenter,
+    LegendPosition::Center,
+];
+
+impl LegendPosition {
+    // where the box sits in the frame, 0 at the left or bottom and 1 at the right or top
+    fn anchor(&self) -> [f32; 2] {
+        match self {
+            LegendPosition::Best | LegendPosition::UpperRight => [1., 1.],
+            LegendPosition::UpperLeft => [0., 1.],
+            LegendPosition::LowerLeft => [0., 0.],
+            LegendPosition::LowerRight => [1., 0.],
+            LegendPosition::Right | LegendPosition::CenterRight => [1., 0.5],
+            LegendPosition::CenterLeft => [0., 0.5],
+            LegendPosition::LowerCenter => [0.5, 0.],
+            LegendPosition::UpperCenter => [0.5, 1.],
+            LegendPosition::Center => [0.5, 0.5],
+        }
+    }
+}
+
+// how a series is drawn in the legend
+#[derive(Clone, Debug, PartialEq)]
+pub enum Swatch {
+    // radius like the circles, in clip units of the height
+    Marker { marker: Marker, color: [f32; 4], radius: f32, outline: Option<([f32; 4], f32)> },
+    // width in pixels
+    Line { color: [f32; 4], width: f32 },
+    Patch { color: [f32; 4] },
+}
+
+// something plotted, listed in the legend once labelled
+#[derive(Clone, Debug)]
+pub struct Series {
+    pub label: Option<String>,
+    pub swatch: Swatch,
+}
+
+// framed box with a swatch and the label of every labelled series
+#[derive(Clone, Debug)]
+pub struct Legend {
+    pub position: LegendPosition,
+    // in pixels
+    pub font_size: f32,
+    pub padding: f32,
+    // between the box and the axes frame
+    pub margin: f32,
+    pub text_color: [f32; 4],
+    pub background: [f32; 4],
+    pub edge_color: [f32; 4],
+}
+
+// what the legend adds to the overlay, in clip space with y up
+#[derive(Default)]
+pub(crate) struct LegendShapes {
+    pub rectangles: Vec<Rectangle>,
+    pub segments: Vec<Segment>,
+    pub circles: Vec<Circle>,
+    pub texts: Vec<Text>,
+}
+
+impl Legend {
+    pub fn new(position: LegendPosition) -> Self {
+        Self {
+            position,
+            font_size: 12.,
+            padding: 6.,
+            margin: 8.,
+            text_color: [1., 1., 1., 1.],
+            background: [0.1, 0.1, 0.1, 0.8],
+            edge_color: [1., 1., 1., 1.],
+        }
+    }
+
+    pub fn font_size(mut self, size: f32) -> Self {
+        self.font_size = size;
+        self
+    }
+
+    pub fn colors(mut self, text: [f32; 4], background: [f32; 4], edge: [f32; 4]) -> Self {
+        self.text_color = text;
+        self.background = background;
+        self.edge_color = edge;
+        self
+    }
+
+    fn row_height(&self) -> f32 {
+        1.5 * self.font_size
+    }
+
+    fn swatch_width(&self) -> f32 {
+        2. * self.font_size
+    }
+
+    // width and height of the box in pixels
+    fn size(&self, labels: &[&str]) -> [f32; 2] {
+        let text_width = labels.iter()
+            .map(|label| text::text_width(label, self.font_size))
+            .fold(0., f32::max);
+
+        [
+            2. * self.padding + self.swatch_width() + 0.5 * self.font_size + text_width,
+            2. * self.padding + labels.len() as f32 * self.row_height(),
+        ]
+    }
+
+    // [[left, right], [bottom, top]] of the box placed inside the frame
+    fn place(&self, position: LegendPosition, size: [f32; 2], frame: [[f32; 2]; 2], pixel: [f32; 2]) -> [[f32; 2]; 2] {
+        let anchor = position.anchor();
+        [0, 1].map(|i| {
+            let [start, end] = frame[i];
+            let (margin, size) = (self.margin * pixel[i], size[i] * pixel[i]);
+            let low = start + margin + anchor[i] * (end - start - 2. * margin - size);
+            [low, low + size]
+        })
+    }
+
+    // points are the data in clip space with y up, only used by `Best`
+    fn rect(&self, size: [f32; 2], frame: [[f32; 2]; 2], pixel: [f32; 2], points: &[[f32; 2]]) -> [[f32; 2]; 2] {
+        if self.position != LegendPosition::Best {
+            return self.place(self.position, size, frame, pixel);
+        }
+
+        let covered = |[[x0, x1], [y0, y1]]: [[f32; 2]; 2]| points.iter()
+            .filter(|[x, y]| (x0..=x1).contains(x) && (y0..=y1).contains(y))
+            .count();
+
+        CANDIDATES.iter()
+            .map(|position| self.place(*position, size, frame, pixel))
+            .enumerate()
+            .min_by_key(|(i, rect)| (covered(*rect), *i))
+            .map(|(_, rect)| rect)
+            .unwrap()
+    }
+
+    pub(crate) fn shapes(&self, series: &[Series], frame: [[f32; 2]; 2], pixel: [f32; 2], points: &[[f32; 2]]) -> LegendShapes {
+        let entries: Vec<(&str, &Swatch)> = series.iter()
+            .filter_map(|s| s.label.as_deref().map(|label| (label, &s.swatch)))
+            .collect();
+        if entries.is_empty() {
+            return LegendShapes::default();
+        }
+
+        let labels: Vec<&str> = entries.iter().map(|(label, _)| *label).collect();
+        let [[x0, x1], [y0, y1]] = self.rect(self.size(&labels), frame, pixel, points);
+
+        let mut shapes = LegendShapes::default();
+        shapes.rectangles.push(Rectangle::from_corners([x0, y0], [x1, y1], self.background));
+        shapes.segments.extend(Segment::polyline(
+            &[[x0, y0, 0.], [x1, y0, 0.], [x1, y1, 0.], [x0, y1, 0.], [x0, y0, 0.]],
+            1.,
+            self.edge_color,
+            Cap::Square,
+        ));
+
+        let row = self.row_height();
+        let left = x0 + self.padding * pixel[0];
+        let right = left + self.swatch_width() * pixel[0];
+        for (i, (label, swatch)) in entries.into_iter().enumerate() {
+            let y = y1 - (self.padding + (i as f32 + 0.5) * row) * pixel[1];
+            let half = 0.35 * row * pixel[1];
+
+            match swatch {
+                Swatch::Marker { marker, color, radius, outline } => {
+                    let radius = radius.min(half);
+                    let (outline_color, outline_width) = outline.unwrap_or((*color, 0.));
+                    shapes.circles.push(Circle::new(radius, [0.5 * (left + right), y, 0.], *color)
+                        .with_outline(outline_color, outline_width)
+                        .with_marker(*marker));
+                }
+                Swatch::Line { color, width } => {
+                    shapes.segments.push(Segment::new([left, y, 0.], [right, y, 0.], width.min(0.5 * row), *color));
+                }
+                Swatch::Patch { color } => {
+                    shapes.rectangles.push(Rectangle::from_corners([left, y - half], [right, y + half], *color));
+                }
+            }
+
+            shapes.texts.push(Text::new([right + 0.5 * self.font_size * pixel[0], y], label)
+                .align(Align::Start, Align::Center)
+                .size(self.font_size)
+                .color(self.text_color));
+        }
+
+        shapes
+    }
+}
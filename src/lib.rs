@@ -0,0 +1,38 @@
+pub mod plotter;
+pub mod plot;
+pub mod camera;
+pub mod fft;
+pub mod wav;
+pub mod table;
+pub mod npy;
+pub mod ticks;
+pub mod scale;
+pub mod axes;
+pub mod colormap;
+pub mod colorbar;
+pub mod heatmap;
+pub mod text;
+pub mod legend;
+pub mod picking;
+
+mod window_surface;
+mod circles;
+mod manager;
+mod circle_manadger;
+mod instance_buffer;
+pub mod lines;
+mod line_manager;
+pub mod rectangles;
+pub mod markers;
+mod rectangle_manager;
+mod text_manager;
+mod heatmap_manager;
+mod renderer;
+mod navigation;
+mod offscreen;
+mod texture;
+mod colormap_data;
+
+pub use plotter::Plotter;
+pub use plot::Plot;
+pub use camera::Camera;
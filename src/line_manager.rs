@@ -0,0 +1,117 @@
+use std::{collections::HashMap, sync::Arc};
+
+use vulkano::{buffer::{allocator::{SubbufferAllocator, SubbufferAllocatorCreateInfo}, BufferUsage, Subbuffer}, command_buffer::{allocator::CommandBufferAllocator, AutoCommandBufferBuilder, PrimaryAutoCommandBuffer}, descriptor_set::allocator::StandardDescriptorSetAllocator, device::Device, memory::allocator::{MemoryTypeFilter, StandardMemoryAllocator}, pipeline::{graphics::vertex_input::Vertex, GraphicsPipeline}, render_pass::RenderPass, shader::ShaderModule};
+
+use crate::{camera::CameraSlots, manager::{create_uniform_buffer, create_descriptorset, create_instance_buffer, create_pipeline, create_vertex_buffer, draw_instances, layer_instances}, circles::MyVertex, lines::{self, vs, Segment}, instance_buffer::{Changes, InstanceBuffer}, plot::{Layer, Overlay, Plot, PlotId}};
+
+pub struct LineManager {
+    device: Arc<Device>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    vs: Arc<ShaderModule>,
+    fs: Arc<ShaderModule>,
+    vertex_buffer: Subbuffer<[MyVertex]>,
+    // data lines of each plot, updated in place. line widths are in pixels
+    instances: InstanceBuffer<Segment>,
+    // axes of each plot, rebuilt when its limits change
+    overlays: HashMap<PlotId, Subbuffer<[Segment]>>,
+    pipeline: Option<Arc<GraphicsPipeline>>,
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
+    subbuffer_allocator : SubbufferAllocator
+}
+
+impl LineManager {
+    pub fn new(device: Arc<Device>, memory_allocator: Arc<StandardMemoryAllocator>) -> Self {
+        let vs = lines::vs::load(device.clone()).unwrap();
+        let fs = lines::fs::load(device.clone()).unwrap();
+        let descriptor_set_allocator = StandardDescriptorSetAllocator::new(device.clone(), Default::default());
+        let subbuffer_allocator = SubbufferAllocator::new(memory_allocator.clone(), SubbufferAllocatorCreateInfo {
+            buffer_usage: BufferUsage::UNIFORM_BUFFER,
+            memory_type_filter: MemoryTypeFilter::HOST_SEQUENTIAL_WRITE | MemoryTypeFilter::PREFER_DEVICE,
+            ..Default::default()
+        });
+
+        Self {
+            device,
+            vs,
+            fs,
+            pipeline: None,
+            vertex_buffer: create_vertex_buffer(memory_allocator.clone()),
+            instances: InstanceBuffer::new(memory_allocator.clone()),
+            memory_allocator,
+            overlays: HashMap::new(),
+            descriptor_set_allocator,
+            subbuffer_allocator
+        }
+    }
+
+    pub fn create_buffers<'a, I>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        plots : I,
+        changes: &mut Changes,
+        ) -> &mut Self
+    where
+        I: IntoIterator<Item = &'a Plot> + Clone,
+    {
+        let ids: Vec<PlotId> = plots.clone().into_iter().map(|plot| plot.id()).collect();
+        self.instances.retain(&ids);
+        self.overlays.retain(|id, _| ids.contains(id));
+        for plot in plots {
+            self.instances.sync(builder, plot.id(), plot.generation(), plot.lines.len(), |start| plot.scaled_lines(start), changes);
+        }
+
+        self
+    }
+
+    pub fn create_overlay_buffers(&mut self, overlays: &[(PlotId, Overlay)]) -> &mut Self {
+        for (id, overlay) in overlays {
+            match create_instance_buffer(self.memory_allocator.clone(), overlay.segments.clone()) {
+                Some(buffer) => self.overlays.insert(*id, buffer),
+                None => self.overlays.remove(id),
+            };
+        }
+
+        self
+    }
+
+    pub fn build_pipeline(
+        &mut self,
+        render_pass: Arc<RenderPass>,
+    ) -> &mut Self {
+        // the edges are anti-aliased through the alpha channel
+        self.pipeline = Some(create_pipeline(
+            self.device.clone(),
+            &self.vs,
+            &self.fs,
+            &[MyVertex::per_vertex(), Segment::per_instance()],
+            render_pass,
+            ));
+        self
+    }
+
+    pub fn draw<A>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer<A>, A>,
+        plot: &Plot,
+        layer: Layer,
+        cameras: &mut CameraSlots,
+    ) where
+        A: CommandBufferAllocator,
+    {
+        // the axes go through their own transform
+        let Some(instances) = layer_instances(&self.instances, &self.overlays, plot.id(), layer) else { return };
+        let transform = plot.transform(layer);
+
+        let pipeline = self.pipeline.clone().unwrap();
+
+        let uniform_buffer = create_uniform_buffer(&self.subbuffer_allocator, plot.id(), cameras, vs::UBO {
+            projection: plot.camera.get_projection().to_cols_array_2d(),
+            modelview: plot.camera.get_view().to_cols_array_2d(),
+            data: transform.to_cols_array_2d(),
+            viewport: plot.extent().map(|v| v as f32),
+        });
+
+        let descriptor_set = create_descriptorset(pipeline.clone(), &self.descriptor_set_allocator, uniform_buffer);
+        draw_instances(builder, pipeline, descriptor_set, self.vertex_buffer.clone(), instances);
+    }
+}
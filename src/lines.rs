@@ -0,0 +1,261 @@
+use vulkano::{buffer::BufferContents, pipeline::graphics::vertex_input::Vertex};
+
+// shape of the free ends of a polyline, inner ends are round joins, or mitered ones when the line is translucent
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cap {
+    Butt,
+    Square,
+    Round,
+}
+
+// must match the constants of the fragment shader
+const JOIN: u32 = 0;
+
+impl Cap {
+    fn id(&self) -> u32 {
+        match self {
+            Cap::Butt => 1,
+            Cap::Square => 2,
+            Cap::Round => 3,
+        }
+    }
+}
+
+#[derive(BufferContents, Vertex, Clone, Debug)]
+#[repr(C)]
+pub struct Segment {
+    #[format(R32G32B32_SFLOAT)]
+    pub start: [f32; 3],
+    #[format(R32G32B32_SFLOAT)]
+    pub end: [f32; 3],
+    #[format(R32G32B32A32_SFLOAT)]
+    pub color: [f32; 4],
+    #[format(R32_SFLOAT)]
+    pub width: f32,
+    #[format(R32G32_UINT)]
+    pub caps: [u32; 2],
+    // the other ends of the neighbouring segments, the own ends when there are none
+    #[format(R32G32B32_SFLOAT)]
+    pub previous: [f32; 3],
+    #[format(R32G32B32_SFLOAT)]
+    pub next: [f32; 3],
+}
+
+pub mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec3 local_position;
+
+            //per-instance data
+            layout(location = 1) in vec3 start;
+            layout(location = 2) in vec3 end;
+            layout(location = 3) in vec4 color;
+            layout(location = 4) in float width;
+            layout(location = 5) in uvec2 caps;
+            layout(location = 6) in vec3 previous;
+            layout(location = 7) in vec3 next;
+
+            // uniform data
+            layout(set = 0, binding = 0) uniform UBO
+            {
+                mat4 projection;
+                mat4 modelview;
+                // data coordinates to [-1; 1]
+                mat4 data;
+                vec2 viewport;
+            } ubo;
+
+            //out
+            layout(location = 0) out vec2 o_local;
+            layout(location = 1) out vec4 o_color;
+            layout(location = 2) out float o_length;
+            layout(location = 3) out float o_half_width;
+            layout(location = 4) flat out uvec2 o_caps;
+            layout(location = 5) flat out vec4 o_joins;
+
+            const uint JOIN = 0;
+            // how far a miter reaches past the join, in half widths
+            const float MITER_LIMIT = 2.;
+
+            // direction of the line splitting two segments at a join, in the local frame of the segment,
+            // zero without a neighbour so the end stays round
+            vec2 join_direction(vec2 dir, vec2 other) {
+                if (length(other) == 0.)
+                    return vec2(0.);
+                vec2 tangent = dir + other;
+                // a segment turning back on itself is simply cut
+                if (length(tangent) < 1e-3)
+                    return vec2(1., 0.);
+                tangent = normalize(tangent);
+                return vec2(dot(tangent, dir), dot(tangent, vec2(-dir.y, dir.x)));
+            }
+
+            void main() {
+                vec4 a = ubo.projection * ubo.modelview * ubo.data * vec4(start, 1.0);
+                vec4 b = ubo.projection * ubo.modelview * ubo.data * vec4(end, 1.0);
+
+                // the quad is expanded in pixels so the width does not depend on the direction
+                vec2 half_viewport = 0.5 * ubo.viewport;
+                vec2 pa = a.xy / a.w * half_viewport;
+                vec2 pb = b.xy / b.w * half_viewport;
+
+                float len = length(pb - pa);
+                vec2 dir = len > 0. ? (pb - pa) / len : vec2(1., 0.);
+                vec2 normal = vec2(-dir.y, dir.x);
+
+                vec4 p = ubo.projection * ubo.modelview * ubo.data * vec4(previous, 1.0);
+                vec4 n = ubo.projection * ubo.modelview * ubo.data * vec4(next, 1.0);
+                vec2 to_previous = pa - p.xy / p.w * half_viewport;
+                vec2 to_next = n.xy / n.w * half_viewport - pb;
+                vec2 start_join = join_direction(dir, length(to_previous) > 0. ? to_previous / length(to_previous) : vec2(0.));
+                vec2 end_join = join_direction(dir, length(to_next) > 0. ? to_next / length(to_next) : vec2(0.));
+
+                // one more pixel for the anti-aliasing
+                float extent = 0.5 * width + 1.;
+                // translucent joins are mitered so the neighbouring segments never cover the same pixels,
+                // the quad then reaches up to the line splitting them, bounded by the miter limit
+                bool mitered = color.a < 1.;
+                float start_extent = mitered && caps.x == JOIN && start_join != vec2(0.) ? extent * min(abs(start_join.y) / max(start_join.x, 1e-3), 2. * MITER_LIMIT) : extent;
+                float end_extent = mitered && caps.y == JOIN && end_join != vec2(0.) ? extent * min(abs(end_join.y) / max(end_join.x, 1e-3), 2. * MITER_LIMIT) : extent;
+                float along = local_position.x < 0. ? -start_extent : len + end_extent;
+                float across = local_position.y * extent;
+
+                o_local = vec2(along, across);
+                o_color = color;
+                o_length = len;
+                o_half_width = 0.5 * width;
+                o_caps = caps;
+                o_joins = vec4(start_join, end_join);
+
+                gl_Position = vec4((pa + dir * along + normal * across) / half_viewport, a.z / a.w, 1.0);
+            }
+            ",
+    }
+}
+
+pub mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: r"
+            #version 460
+
+            const uint JOIN = 0;
+            const uint BUTT = 1;
+            const uint SQUARE = 2;
+
+            layout(location = 0) in vec2 local;
+            layout(location = 1) in vec4 color;
+            layout(location = 2) in float len;
+            layout(location = 3) in float half_width;
+            layout(location = 4) flat in uvec2 caps;
+            layout(location = 5) flat in vec4 joins;
+
+            layout(location = 0) out vec4 f_color;
+
+            const float MITER_LIMIT = 2.;
+
+            // distance to the line center, in pixels, or a negative value past the line splitting the two
+            // segments, p is relative to the join and sign is 1 at the start and -1 at the end
+            float miter_distance(vec2 p, vec2 join, float sign) {
+                if (sign * dot(p, join) < 0.)
+                    return -1.;
+                if (sign * p.x >= 0.)
+                    return abs(p.y);
+                // past the end the miter is cut across where it reaches too far from the join
+                float reach = abs(dot(p, vec2(-join.y, join.x)));
+                return max(abs(p.y), reach - (MITER_LIMIT - 1.) * half_width);
+            }
+
+            // distance to the line center, in pixels, t pixels past the end of the segment
+            float cap_distance(float t, float y, uint cap) {
+                if (cap == BUTT)
+                    return max(abs(y), t + half_width);
+                if (cap == SQUARE)
+                    return max(abs(y), t);
+                return length(vec2(t, y));
+            }
+
+            void main() {
+                bool start_miter = color.a < 1. && caps.x == JOIN && joins.xy != vec2(0.);
+                bool end_miter = color.a < 1. && caps.y == JOIN && joins.zw != vec2(0.);
+                float start = start_miter ? miter_distance(local, joins.xy, 1.) : 0.;
+                float end = end_miter ? miter_distance(local - vec2(len, 0.), joins.zw, -1.) : 0.;
+                if (start < 0. || end < 0.)
+                    discard;
+
+                float distance;
+                if (!start_miter && local.x < 0.)
+                    distance = cap_distance(-local.x, local.y, caps.x);
+                else if (!end_miter && local.x > len)
+                    distance = cap_distance(local.x - len, local.y, caps.y);
+                else
+                    distance = max(abs(local.y), max(start, end));
+
+                float alpha = clamp(half_width - distance + 0.5, 0., 1.);
+                if (alpha <= 0.)
+                    discard;
+
+                f_color = vec4(color.rgb, color.a * alpha);
+            }
+        ",
+    }
+}
+
+impl Segment {
+    pub fn new(start: [f32; 3], end: [f32; 3], width: f32, color: [f32; 4]) -> Self {
+        Self {
+            start,
+            end,
+            color,
+            width,
+            caps: [JOIN, JOIN],
+            previous: start,
+            next: end,
+        }
+    }
+
+    // segments of the polyline going through the points, each knowing its neighbours for the joins
+    pub fn polyline(points: &[[f32; 3]], width: f32, color: [f32; 4], cap: Cap) -> Vec<Self> {
+        let mut segments: Vec<Self> = points
+            .windows(2)
+            .map(|p| Self::new(p[0], p[1], width, color))
+            .collect();
+
+        for i in 1..segments.len() {
+            segments[i].previous = segments[i - 1].start;
+            segments[i - 1].next = segments[i].end;
+        }
+
+        if let Some(first) = segments.first_mut() {
+            first.caps[0] = cap.id();
+        }
+        if let Some(last) = segments.last_mut() {
+            last.caps[1] = cap.id();
+        }
+
+        segments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polyline_neighbours() {
+        let points = [[0., 0., 0.], [1., 0., 0.], [1., 1., 0.]];
+        let segments = Segment::polyline(&points, 2., [0., 0., 0., 0.5], Cap::Butt);
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].caps, [Cap::Butt.id(), JOIN]);
+        assert_eq!(segments[1].caps, [JOIN, Cap::Butt.id()]);
+        // the free ends point at themselves
+        assert_eq!(segments[0].previous, points[0]);
+        assert_eq!(segments[1].next, points[2]);
+        assert_eq!(segments[0].next, points[2]);
+        assert_eq!(segments[1].previous, points[0]);
+    }
+}
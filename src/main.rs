@@ -1,33 +1,355 @@
-mod plotter;
-mod plot;
-mod window_surface;
-mod circles;
-mod circle_manadger;
-mod renderer;
-mod camera;
-mod fft;
+use std::{path::{Path, PathBuf}, process};
 
-use std::env;
+use clap::{Args, Parser, Subcommand};
+use num::complex::Complex;
+use tipe::{colorbar::Colorbar, colormap::Colormap, fft, heatmap::Heatmap, markers::{Marker, Markers}, npy::{self, NpyArray}, scale::Scale, table::{ColumnRef, Table}, wav::Wav, Plotter};
+
+// dynamic range of spectrograms, in dB
+const DYNAMIC_RANGE: f64 = 80.;
+
+#[derive(Parser)]
+#[command(name = "tipe", version, about = "Plot and analyse data files")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Scatter plot of two columns of a csv/tsv/txt file or two arrays of a npz file
+    Scatter {
+        file: PathBuf,
+        /// column name or index of the x values
+        #[arg(long)]
+        x: String,
+        /// column name or index of the y values
+        #[arg(long)]
+        y: String,
+        #[command(flatten)]
+        style: Style,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Amplitude spectrum of a signal
+    Fft {
+        #[command(flatten)]
+        signal: Signal,
+        /// logarithmic frequency axis, the 0 Hz bin is left out
+        #[arg(long)]
+        logx: bool,
+        /// logarithmic amplitude axis
+        #[arg(long)]
+        logy: bool,
+        #[command(flatten)]
+        style: Style,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Spectrogram of a signal
+    Spectrogram {
+        #[command(flatten)]
+        signal: Signal,
+        /// fft size of each frame, a power of two
+        #[arg(long, default_value_t = 256)]
+        window: usize,
+        /// number of samples between two frames, defaults to half a window
+        #[arg(long)]
+        hop: Option<usize>,
+        /// viridis, magma, inferno, plasma, cividis, grey, coolwarm or twilight
+        #[arg(long, default_value = "viridis", value_parser = parse_colormap)]
+        colormap: Colormap,
+        #[command(flatten)]
+        output: Output,
+    },
+}
+
+#[derive(Args)]
+struct Signal {
+    /// wav, npy or csv/tsv/txt file
+    file: PathBuf,
+    /// column name or index, for tables
+    #[arg(long)]
+    column: Option<String>,
+    /// channel, for wav files
+    #[arg(long, default_value_t = 0)]
+    channel: usize,
+    /// sample rate in Hz, read from the file for wav files
+    #[arg(long)]
+    rate: Option<f64>,
+}
+
+#[derive(Args)]
+struct Style {
+    /// colour name, #rrggbb[aa] or r,g,b[,a] in [0; 1]
+    #[arg(long, default_value = "white", value_parser = parse_color)]
+    color: [f32; 4],
+    /// marker radius, for scatter plots
+    #[arg(long, default_value_t = 0.01)]
+    radius: f32,
+    /// circle, square, triangle, diamond, cross, plus or star, for scatter plots
+    #[arg(long, default_value = "circle", value_parser = parse_marker)]
+    marker: Marker,
+    /// line width in pixels, for line plots
+    #[arg(long, default_value_t = 1.5)]
+    width: f32,
+}
+
+#[derive(Args)]
+struct Output {
+    /// render to a png file instead of opening a window
+    #[arg(long)]
+    out: Option<PathBuf>,
+    /// width of the png file in pixels
+    #[arg(long, default_value_t = 800)]
+    width: u32,
+    /// height of the png file in pixels
+    #[arg(long, default_value_t = 600)]
+    height: u32,
+}
+
+impl Output {
+    // without window when rendering to a file, so it works without a display
+    fn plotter(&self) -> Plotter {
+        match self.out {
+            Some(_) => Plotter::headless(),
+            None => Plotter::new(),
+        }
+    }
+
+    fn show(&self, plt: &mut Plotter) -> Result<(), String> {
+        match &self.out {
+            Some(path) => plt.save_png(path, self.width, self.height)
+                .map_err(|e| format!("{}: {e}", path.display())),
+            None => {
+                plt.show();
+                Ok(())
+            }
+        }
+    }
+}
 
 fn main() {
+    if let Err(e) = run(Cli::parse()) {
+        eprintln!("tipe: {e}");
+        process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    match cli.command {
+        Command::Scatter { file, x, y, style, output } => {
+            let (xs, ys) = load_columns(&file, &x, &y)?;
+            let (xs, ys): (Vec<f64>, Vec<f64>) = xs.into_iter()
+                .zip(ys)
+                .filter(|(a, b)| a.is_finite() && b.is_finite())
+                .unzip();
+
+            let mut plt = output.plotter();
+            plt.markers(Markers::new(to_f32(&xs), to_f32(&ys), style.radius, style.color).marker(style.marker));
+            plt.current_plot_mut().xlabel(x).ylabel(y);
+            output.show(&mut plt)
+        }
+        Command::Fft { signal, logx, logy, style, output } => {
+            let (values, rate) = load_signal(&signal)?;
+            let n = values.len().next_power_of_two();
+            let spectrum = fft::my_fft(&values.iter()
+                .map(|v| Complex::new(*v, 0.))
+                .chain(std::iter::repeat(Complex::new(0., 0.)))
+                .take(n)
+                .collect());
+
+            // positive frequencies only
+            let (freq, amplitude): (Vec<f64>, Vec<f64>) = fft::fftfreq(n, 1. / rate)
+                .into_iter()
+                .zip(spectrum)
+                .take(n / 2)
+                .map(|(f, x)| (f, x.norm() / n as f64))
+                .unzip();
 
-    env::set_var("RUST_BACKTRACE", "1");
-    let mut plt = plotter::Plotter::new();
+            let mut plt = output.plotter();
+            plt.plot(to_f32(&freq), to_f32(&amplitude), style.width, style.color);
+            plt.current_plot_mut().xlabel("frequency (Hz)").ylabel("amplitude");
+            if logx {
+                plt.xscale(Scale::Log10);
+            }
+            if logy {
+                plt.yscale(Scale::Log10);
+            }
+            output.show(&mut plt)
+        }
+        Command::Spectrogram { signal, window, hop, colormap, output } => {
+            if !window.is_power_of_two() {
+                return Err(format!("--window must be a power of two, got {window}"));
+            }
+
+            let (values, rate) = load_signal(&signal)?;
+            let hop = hop.unwrap_or(window / 2).max(1);
+            let frames = fft::stft(&values, window, hop);
+            if frames.is_empty() {
+                return Err(format!("the signal is shorter than a window ({} < {window})", values.len()));
+            }
+
+            let bins = window / 2;
+            let db: Vec<Vec<f64>> = frames.iter()
+                .map(|frame| frame[..bins].iter().map(|x| 20. * (x.norm() + 1e-12).log10()).collect())
+                .collect();
+            let max = db.iter().flatten().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+            // one row per frequency bin, one column per frame, in seconds and hertz
+            let values = (0..bins)
+                .flat_map(|k| db.iter().map(move |frame| frame[k] as f32))
+                .collect();
+            let duration = (frames.len() * hop) as f64 / rate;
+            let heatmap = Heatmap::new(values, [bins, frames.len()])
+                .extent([0., duration as f32], [0., (rate / 2.) as f32])
+                .colormap(colormap)
+                .range((max - DYNAMIC_RANGE) as f32, max as f32);
+
+            let mut plt = output.plotter();
+            plt.colorbar(Colorbar::from_heatmap(&heatmap).label("dB"));
+            plt.heatmap(heatmap);
+            plt.current_plot_mut()
+                .set_margin(0.)
+                .xlabel("time (s)")
+                .ylabel("frequency (Hz)");
+            output.show(&mut plt)
+        }
+    }
+}
+
+fn to_f32(values: &[f64]) -> Vec<f32> {
+    values.iter().map(|v| *v as f32).collect()
+}
+
+fn column_ref(column: &str) -> ColumnRef {
+    match column.parse::<usize>() {
+        Ok(index) => ColumnRef::Index(index),
+        Err(_) => ColumnRef::Name(column.to_string()),
+    }
+}
+
+fn extension(file: &Path) -> String {
+    file.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase()
+}
+
+fn load_columns(file: &Path, x: &str, y: &str) -> Result<(Vec<f64>, Vec<f64>), String> {
+    let error = |e: std::io::Error| format!("{}: {e}", file.display());
+
+    if extension(file) == "npz" {
+        let arrays = npy::read_npz(file).map_err(error)?;
+        let get = |name: &str| arrays.get(name)
+            .ok_or_else(|| format!("{}: no array named {name}", file.display()))?
+            .to_f64()
+            .ok_or_else(|| format!("{}: {name} is not a real array", file.display()));
+
+        return Ok((get(x)?, get(y)?));
+    }
+
+    let table = Table::read(file).map_err(error)?;
+    let get = |column: &str| table.f64(column_ref(column))
+        .ok_or_else(|| format!("{}: no column {column}, available columns are {:?}", file.display(), table.names()));
+
+    Ok((get(x)?, get(y)?))
+}
+
+// returns the samples and the sample rate
+fn load_signal(signal: &Signal) -> Result<(Vec<f64>, f64), String> {
+    let file = &signal.file;
+    let error = |e: std::io::Error| format!("{}: {e}", file.display());
+
+    match extension(file).as_str() {
+        "wav" => {
+            let wav = Wav::read(file).map_err(error)?;
+            let values = wav.channels
+                .get(signal.channel)
+                .ok_or_else(|| format!("{}: no channel {}, the file has {}", file.display(), signal.channel, wav.n_channels()))?
+                .clone();
+
+            Ok((values, signal.rate.unwrap_or(wav.sample_rate as f64)))
+        }
+        "npy" => {
+            let values = NpyArray::read(file).map_err(error)?
+                .to_f64()
+                .ok_or_else(|| format!("{}: not a real array", file.display()))?;
+
+            Ok((values, signal.rate.unwrap_or(1.)))
+        }
+        _ => {
+            let table = Table::read(file).map_err(error)?;
+            let column = signal.column.as_deref().unwrap_or("0");
+            let values = table.f64(column_ref(column))
+                .ok_or_else(|| format!("{}: no column {column}, available columns are {:?}", file.display(), table.names()))?;
+
+            Ok((values, signal.rate.unwrap_or(1.)))
+        }
+    }
+}
+
+fn parse_colormap(colormap: &str) -> Result<Colormap, String> {
+    Ok(match colormap.to_ascii_lowercase().as_str() {
+        "viridis" => Colormap::Viridis,
+        "magma" => Colormap::Magma,
+        "inferno" => Colormap::Inferno,
+        "plasma" => Colormap::Plasma,
+        "cividis" => Colormap::Cividis,
+        "grey" | "gray" => Colormap::Grey,
+        "coolwarm" => Colormap::Coolwarm,
+        "twilight" => Colormap::Twilight,
+        _ => return Err(format!("unknown colormap `{colormap}`")),
+    })
+}
+
+fn parse_marker(marker: &str) -> Result<Marker, String> {
+    Ok(match marker.to_ascii_lowercase().as_str() {
+        "circle" | "o" => Marker::Circle,
+        "square" | "s" => Marker::Square,
+        "triangle" | "^" => Marker::Triangle,
+        "diamond" | "d" => Marker::Diamond,
+        "cross" | "x" => Marker::Cross,
+        "plus" | "+" => Marker::Plus,
+        "star" | "*" => Marker::Star,
+        _ => return Err(format!("unknown marker `{marker}`")),
+    })
+}
 
-    let x1 : Vec<f32> = (-1..=1).map(|f| f as f32).collect();
-    let y1 = x1.clone();
-    let radius = 0.5;
+fn parse_color(color: &str) -> Result<[f32; 4], String> {
+    let named = match color {
+        "white" => Some([1., 1., 1., 1.]),
+        "black" => Some([0., 0., 0., 1.]),
+        "red" => Some([1., 0., 0., 1.]),
+        "green" => Some([0., 1., 0., 1.]),
+        "blue" => Some([0., 0., 1., 1.]),
+        "yellow" => Some([1., 1., 0., 1.]),
+        "cyan" => Some([0., 1., 1., 1.]),
+        "magenta" => Some([1., 0., 1., 1.]),
+        _ => None,
+    };
+    if let Some(color) = named {
+        return Ok(color);
+    }
 
-    let x2 = vec![0.];
-    let y2 = vec![0.];
+    let error = || format!("invalid colour {color:?}");
+    let mut rgba = [1.; 4];
 
-    let mut x3 = x1.clone();
-    x3.reverse();
-    let y3 = y1.clone();
+    if let Some(hex) = color.strip_prefix('#') {
+        if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+            return Err(error());
+        }
+        for (i, c) in rgba.iter_mut().enumerate().take(hex.len() / 2) {
+            *c = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| error())? as f32 / 255.;
+        }
+        return Ok(rgba);
+    }
 
-    plt.scatter(x1, y1, radius, [1., 0., 0., 1.])
-        .scatter(x3, y3, radius, [1., 1., 1., 1.])
-       .new_plot()
-       .scatter(x2, y2, radius, [1., 1., 0., 1.])
-       .show();
+    let values = color.split(',')
+        .map(|v| v.trim().parse::<f32>().map_err(|_| error()))
+        .collect::<Result<Vec<_>, _>>()?;
+    if !(values.len() == 3 || values.len() == 4) {
+        return Err(error());
+    }
+    rgba[..values.len()].copy_from_slice(&values);
+    Ok(rgba)
 }
@@ -0,0 +1,199 @@
+use std::{collections::HashMap, sync::Arc};
+
+use vulkano::{buffer::{allocator::SubbufferAllocator, Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer}, command_buffer::{allocator::CommandBufferAllocator, AutoCommandBufferBuilder, PrimaryAutoCommandBuffer}, descriptor_set::{allocator::{StandardDescriptorSetAlloc, StandardDescriptorSetAllocator}, PersistentDescriptorSet, WriteDescriptorSet}, device::Device, image::SampleCount, memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator}, pipeline::{graphics::{color_blend::{AttachmentBlend, ColorBlendAttachmentState, ColorBlendState}, input_assembly::InputAssemblyState, multisample::MultisampleState, rasterization::RasterizationState, vertex_input::{VertexBufferDescription, VertexDefinition}, viewport::ViewportState, GraphicsPipelineCreateInfo}, DynamicState, layout::PipelineDescriptorSetLayoutCreateInfo, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout, PipelineShaderStageCreateInfo}, render_pass::{RenderPass, Subpass}, shader::ShaderModule};
+
+use crate::{camera::{camera_slot, CameraSlots}, circles::{Circle, MyVertex}, instance_buffer::InstanceBuffer, plot::{Layer, PlotId}};
+
+// pipeline drawing the quad of the vertex buffer, once per instance when there are instances, blended
+// through the alpha channel. the viewport and the scissor are set per plot while recording, see `Renderer::record`
+pub(crate) fn create_pipeline(
+    device: Arc<Device>,
+    vs: &Arc<ShaderModule>,
+    fs: &Arc<ShaderModule>,
+    vertex_buffers: &[VertexBufferDescription],
+    render_pass: Arc<RenderPass>,
+    ) -> Arc<GraphicsPipeline>
+{
+    let vs = vs.entry_point("main").unwrap();
+    let fs = fs.entry_point("main").unwrap();
+
+    let vertex_input_state = vertex_buffers
+        .definition(&vs.info().input_interface)
+        .unwrap();
+
+    let stages = [
+        PipelineShaderStageCreateInfo::new(vs),
+        PipelineShaderStageCreateInfo::new(fs),
+    ];
+
+    let layout = PipelineLayout::new(
+        device.clone(),
+        PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
+            .into_pipeline_layout_create_info(device.clone())
+            .unwrap(),
+    )
+    .unwrap();
+
+    let subpass = Subpass::from(render_pass, 0).unwrap();
+
+    GraphicsPipeline::new(
+        device,
+        None,
+        GraphicsPipelineCreateInfo {
+            stages: stages.into_iter().collect(),
+            vertex_input_state: Some(vertex_input_state),
+            input_assembly_state: Some(InputAssemblyState::default()),
+            viewport_state: Some(ViewportState::default()),
+            rasterization_state: Some(RasterizationState::default()),
+            multisample_state: Some(MultisampleState {
+                rasterization_samples: subpass.num_samples().unwrap_or(SampleCount::Sample1),
+                ..Default::default()
+            }),
+            color_blend_state: Some(ColorBlendState::with_attachment_states(
+                subpass.num_color_attachments(),
+                ColorBlendAttachmentState {
+                    blend: Some(AttachmentBlend::alpha()),
+                    ..Default::default()
+                },
+            )),
+            subpass: Some(subpass.into()),
+            dynamic_state: [DynamicState::Viewport, DynamicState::Scissor].into_iter().collect(),
+            ..GraphicsPipelineCreateInfo::layout(layout)
+        },
+    )
+    .unwrap()
+}
+
+// the instances of the plot in the layer, the data ones share a buffer, each overlay has its own.
+// None when there is nothing to draw
+pub(crate) fn layer_instances<T: BufferContents>(
+    instances: &InstanceBuffer<T>,
+    overlays: &HashMap<PlotId, Subbuffer<[T]>>,
+    plot: PlotId,
+    layer: Layer,
+    ) -> Option<(Subbuffer<[T]>, u64, u64)>
+{
+    let (buffer, first, count) = match layer {
+        Layer::Data => {
+            let (first, count) = instances.range(plot)?;
+            (instances.buffer(), first, count)
+        }
+        Layer::Overlay => {
+            let buffer = overlays.get(&plot)?;
+            (Some(buffer.clone()), 0, buffer.len())
+        }
+    };
+
+    buffer.filter(|_| count > 0).map(|buffer| (buffer, first, count))
+}
+
+// the uniform data of a draw, its camera part is rewritten before each submission
+pub(crate) fn create_uniform_buffer<T: BufferContents>(
+    subbuffer_allocator: &SubbufferAllocator,
+    plot: PlotId,
+    cameras: &mut CameraSlots,
+    data: T,
+    ) -> Subbuffer<T>
+{
+    let buffer = subbuffer_allocator
+        .allocate_sized()
+        .unwrap();
+    *buffer.write().unwrap() = data;
+
+    cameras.push((plot, camera_slot(&buffer)));
+    buffer
+}
+
+// one quad per instance, the uniform buffer at binding 0 of the descriptor set
+pub(crate) fn draw_instances<A, T>(
+    builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer<A>, A>,
+    pipeline: Arc<GraphicsPipeline>,
+    descriptor_set: Arc<PersistentDescriptorSet>,
+    vertex_buffer: Subbuffer<[MyVertex]>,
+    (instance_buffer, first, count): (Subbuffer<[T]>, u64, u64),
+    ) where
+    A: CommandBufferAllocator,
+    T: BufferContents,
+{
+    builder
+        .bind_pipeline_graphics(pipeline.clone())
+        .unwrap()
+        .bind_descriptor_sets(
+            PipelineBindPoint::Graphics,
+            pipeline.layout().clone(),
+            0,
+            descriptor_set)
+        .unwrap()
+        .bind_vertex_buffers(0, vertex_buffer.clone())
+        .unwrap()
+        .bind_vertex_buffers(1, instance_buffer)
+        .unwrap()
+        .draw(
+            vertex_buffer.len() as u32,
+            count as u32,
+            0,
+            first as u32,
+        )
+        .unwrap();
+}
+
+pub(crate) fn create_descriptorset(
+    pipeline: Arc<GraphicsPipeline>,
+    descriptor_set_allocator : &StandardDescriptorSetAllocator,
+    buffer: Subbuffer<impl ?Sized>) -> Arc<PersistentDescriptorSet<StandardDescriptorSetAlloc>>
+{
+    let pipeline_layout = pipeline.layout();
+    let descriptor_set_layouts = pipeline_layout.set_layouts();
+
+    let descriptor_set_layout_index = 0;
+    let descriptor_set_layout = descriptor_set_layouts
+        .get(descriptor_set_layout_index)
+        .unwrap();
+
+    PersistentDescriptorSet::new(
+        descriptor_set_allocator,
+        descriptor_set_layout.clone(),
+        [WriteDescriptorSet::buffer(0, buffer)], // 0 is the binding
+        [],
+        )
+        .unwrap()
+}
+
+// buffers can not be empty, None without instances
+pub(crate) fn create_instance_buffer<T: BufferContents>(
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    instances: Vec<T>) -> Option<Subbuffer<[T]>>
+{
+    (!instances.is_empty()).then(|| Buffer::from_iter(
+        memory_allocator,
+        BufferCreateInfo {
+            usage: BufferUsage::VERTEX_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+            ..Default::default()
+        },
+        instances,
+    )
+    .unwrap())
+}
+
+// corners of the quad every instance is drawn on
+pub(crate) fn create_vertex_buffer(memory_allocator: Arc<StandardMemoryAllocator>) -> Subbuffer<[MyVertex]> {
+    Buffer::from_iter(
+        memory_allocator,
+        BufferCreateInfo {
+            usage: BufferUsage::VERTEX_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+            ..Default::default()
+        },
+        Circle::vertex(),
+    )
+    .unwrap()
+}
@@ -0,0 +1,138 @@
+use crate::{circles::Circle, colormap::Colormap, legend::{Series, Swatch}};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Marker {
+    #[default]
+    Circle,
+    Square,
+    Triangle,
+    Diamond,
+    Cross,
+    Plus,
+    Star,
+}
+
+impl Marker {
+    // must match the constants of the circle fragment shader
+    pub(crate) fn id(&self) -> u32 {
+        match self {
+            Marker::Circle => 0,
+            Marker::Square => 1,
+            Marker::Triangle => 2,
+            Marker::Diamond => 3,
+            Marker::Cross => 4,
+            Marker::Plus => 5,
+            Marker::Star => 6,
+        }
+    }
+}
+
+// scatter description, see `Plotter::markers`
+#[derive(Clone, Debug)]
+pub struct Markers {
+    x: Vec<f32>,
+    y: Vec<f32>,
+    radius: f32,
+    color: [f32; 4],
+    // per point, override radius and color
+    sizes: Option<Vec<f32>>,
+    colors: Option<Vec<[f32; 4]>>,
+    values: Option<(Vec<f32>, Colormap, Option<[f32; 2]>)>,
+    marker: Marker,
+    outline: Option<([f32; 4], f32)>,
+    // listed in the legend when set
+    label: Option<String>,
+}
+
+impl Markers {
+    pub fn new(x: Vec<f32>, y: Vec<f32>, radius: f32, color: [f32; 4]) -> Self {
+        Self {
+            x,
+            y,
+            radius,
+            color,
+            sizes: None,
+            colors: None,
+            values: None,
+            marker: Marker::Circle,
+            outline: None,
+            label: None,
+        }
+    }
+
+    // one radius per point, the points without one keep the common radius
+    pub fn sizes(mut self, sizes: Vec<f32>) -> Self {
+        self.sizes = Some(sizes);
+        self
+    }
+
+    // one colour per point
+    pub fn colors(mut self, colors: Vec<[f32; 4]>) -> Self {
+        self.colors = Some(colors);
+        self.values = None;
+        self
+    }
+
+    // colours from the values through the colormap, see `Colormap::map`
+    pub fn values(mut self, values: Vec<f32>, colormap: Colormap, range: Option<[f32; 2]>) -> Self {
+        self.values = Some((values, colormap, range));
+        self.colors = None;
+        self
+    }
+
+    pub fn marker(mut self, marker: Marker) -> Self {
+        self.marker = marker;
+        self
+    }
+
+    // width in pixels, drawn inside the shape
+    pub fn outline(mut self, color: [f32; 4], width: f32) -> Self {
+        self.outline = Some((color, width));
+        self
+    }
+
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    // drawn with the first colour when they differ between points
+    pub(crate) fn series(&self) -> Series {
+        let color = match &self.values {
+            Some((values, colormap, range)) => colormap.map(values, *range).first().copied(),
+            None => self.colors.as_ref().and_then(|c| c.first().copied()),
+        };
+
+        Series {
+            label: self.label.clone(),
+            swatch: Swatch::Marker {
+                marker: self.marker,
+                color: color.unwrap_or(self.color),
+                radius: self.radius,
+                outline: self.outline,
+            },
+        }
+    }
+
+    pub(crate) fn circles(&self) -> Vec<Circle> {
+        let colors = match &self.values {
+            Some((values, colormap, range)) => Some(colormap.map(values, *range)),
+            None => self.colors.clone(),
+        };
+
+        self.x
+            .iter()
+            .zip(self.y.iter())
+            .enumerate()
+            .map(|(i, (a, b))| {
+                let radius = self.sizes.as_ref().and_then(|s| s.get(i)).copied().unwrap_or(self.radius);
+                let color = colors.as_ref().and_then(|c| c.get(i)).copied().unwrap_or(self.color);
+                let (outline_color, outline_width) = self.outline.unwrap_or((color, 0.));
+
+                Circle::new(radius, [*a, *b, 0.], color)
+                    .with_outline(outline_color, outline_width)
+                    .with_marker(self.marker)
+            })
+            .collect()
+    }
+}
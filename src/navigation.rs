@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use winit::event::{ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+
+use crate::{picking::Pick, plot::{Plot, PlotId}};
+
+// limits scale per line of the scroll wheel
+const ZOOM_STEP: f32 = 0.9;
+// pixels of a touchpad scroll counted as one line of a wheel
+const PIXELS_PER_LINE: f32 = 40.;
+// a press and release closer than this in pixels is a click
+const CLICK_DISTANCE: f32 = 3.;
+
+// mouse and keyboard state of a window, turned into changes of the limits of its plots:
+// left drag pans, control and left drag zooms into a box, the wheel zooms around the cursor,
+// R or Home resets the view. hovering a circle shows a tooltip, clicking it picks it
+#[derive(Default)]
+pub(crate) struct Navigation {
+    // in pixels of the window, y down
+    cursor: [f32; 2],
+    modifiers: ModifiersState,
+    // plot under the cursor when the left button went down and where it was
+    drag: Option<(PlotId, [f32; 2])>,
+    // the drag selects a box instead of panning
+    boxing: bool,
+}
+
+// what an event did to the plots of the window
+#[derive(Default)]
+pub(crate) struct Response {
+    // limits or tooltips changed, the window needs to be redrawn
+    pub redraw: bool,
+    // clicked circle
+    pub pick: Option<Pick>,
+}
+
+impl From<bool> for Response {
+    fn from(redraw: bool) -> Self {
+        Self { redraw, pick: None }
+    }
+}
+
+impl Navigation {
+    // figure is the plots of the window
+    pub(crate) fn handle(&mut self, event: &WindowEvent, plots: &mut HashMap<PlotId, Plot>, figure: &[PlotId]) -> Response {
+        let redraw = match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = *modifiers;
+                false
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let cursor = [position.x as f32, position.y as f32];
+                let previous = std::mem::replace(&mut self.cursor, cursor);
+                match self.drag {
+                    Some((id, start)) if self.boxing => {
+                        plots.get_mut(&id).unwrap().set_selection(Some([start, cursor]));
+                        true
+                    }
+                    Some((id, _)) => {
+                        plots.get_mut(&id).unwrap().pan(previous, cursor);
+                        true
+                    }
+                    None => hover(plots, figure, Some(cursor)),
+                }
+            }
+            WindowEvent::CursorLeft { .. } => {
+                let released = self.release(plots);
+                let hidden = hover(plots, figure, None);
+                return Response { redraw: released.redraw || hidden, ..released };
+            }
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => match state {
+                ElementState::Pressed => {
+                    self.drag = under(plots, figure, self.cursor).map(|id| (id, self.cursor));
+                    self.boxing = self.modifiers.ctrl();
+                    // the tooltip would be left behind by panning
+                    hover(plots, figure, None)
+                }
+                ElementState::Released => return self.release(plots),
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_LINE,
+                };
+                match under(plots, figure, self.cursor) {
+                    Some(id) if lines != 0. => {
+                        plots.get_mut(&id).unwrap().zoom(self.cursor, ZOOM_STEP.powf(lines));
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::R | VirtualKeyCode::Home),
+                    ..
+                },
+                ..
+            } => {
+                // the plot under the cursor, or the whole figure when outside of all of them
+                let ids = match under(plots, figure, self.cursor) {
+                    Some(id) => vec![id],
+                    None => figure.to_vec(),
+                };
+                for id in ids {
+                    plots.get_mut(&id).unwrap().reset_view();
+                }
+                true
+            }
+            _ => false,
+        };
+
+        redraw.into()
+    }
+
+    // ends a drag, zooming into the box when one was selected, picking when the cursor barely moved
+    fn release(&mut self, plots: &mut HashMap<PlotId, Plot>) -> Response {
+        let (id, start) = match self.drag.take() {
+            Some(drag) => drag,
+            None => return Response::default(),
+        };
+        let plot = plots.get_mut(&id).unwrap();
+
+        if self.boxing {
+            plot.set_selection(None);
+            plot.zoom_to(start, self.cursor);
+            return true.into();
+        }
+
+        let moved = (self.cursor[0] - start[0]).hypot(self.cursor[1] - start[1]);
+        match moved < CLICK_DISTANCE {
+            true => Response { redraw: false, pick: plot.pick(self.cursor) },
+            false => Response::default(),
+        }
+    }
+}
+
+// updates the tooltips of the figure, true when one changed
+fn hover(plots: &mut HashMap<PlotId, Plot>, figure: &[PlotId], cursor: Option<[f32; 2]>) -> bool {
+    let mut changed = false;
+    for id in figure {
+        changed |= plots.get_mut(id).unwrap().hover(cursor);
+    }
+    changed
+}
+
+fn under(plots: &HashMap<PlotId, Plot>, figure: &[PlotId], cursor: [f32; 2]) -> Option<PlotId> {
+    figure.iter().copied().find(|id| plots[id].contains(cursor))
+}
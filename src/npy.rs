@@ -0,0 +1,534 @@
+use std::{collections::BTreeMap, fs::File, io::{self, BufReader, BufWriter, Read, Seek, Write}, path::Path};
+
+use num::complex::Complex;
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+// numpy element types that can be exchanged
+trait Element: Copy {
+    const KIND: &'static str;
+
+    fn size() -> usize;
+    fn from_bytes(bytes: &[u8], big_endian: bool) -> Self;
+    fn write_le(&self, out: &mut Vec<u8>);
+}
+
+macro_rules! float_element {
+    ($t:ty, $kind:literal) => {
+        impl Element for $t {
+            const KIND: &'static str = $kind;
+
+            fn size() -> usize { std::mem::size_of::<$t>() }
+
+            fn from_bytes(bytes: &[u8], big_endian: bool) -> Self {
+                let bytes = bytes.try_into().unwrap();
+                if big_endian { <$t>::from_be_bytes(bytes) } else { <$t>::from_le_bytes(bytes) }
+            }
+
+            fn write_le(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+        }
+    };
+}
+
+float_element!(f32, "f4");
+float_element!(f64, "f8");
+
+macro_rules! complex_element {
+    ($t:ty, $kind:literal) => {
+        impl Element for Complex<$t> {
+            const KIND: &'static str = $kind;
+
+            fn size() -> usize { 2 * <$t>::size() }
+
+            fn from_bytes(bytes: &[u8], big_endian: bool) -> Self {
+                let (re, im) = bytes.split_at(<$t>::size());
+                Complex::new(<$t>::from_bytes(re, big_endian), <$t>::from_bytes(im, big_endian))
+            }
+
+            fn write_le(&self, out: &mut Vec<u8>) {
+                self.re.write_le(out);
+                self.im.write_le(out);
+            }
+        }
+    };
+}
+
+complex_element!(f32, "c8");
+complex_element!(f64, "c16");
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NpyData {
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+    Complex64(Vec<Complex<f32>>),
+    Complex128(Vec<Complex<f64>>),
+}
+
+impl NpyData {
+    pub fn len(&self) -> usize {
+        match self {
+            NpyData::F32(v) => v.len(),
+            NpyData::F64(v) => v.len(),
+            NpyData::Complex64(v) => v.len(),
+            NpyData::Complex128(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    fn descr(&self) -> String {
+        let kind = match self {
+            NpyData::F32(_) => f32::KIND,
+            NpyData::F64(_) => f64::KIND,
+            NpyData::Complex64(_) => Complex::<f32>::KIND,
+            NpyData::Complex128(_) => Complex::<f64>::KIND,
+        };
+        format!("<{kind}")
+    }
+
+    fn write_le(&self, out: &mut Vec<u8>) {
+        fn write<T: Element>(values: &[T], out: &mut Vec<u8>) {
+            out.reserve(values.len() * T::size());
+            values.iter().for_each(|v| v.write_le(out));
+        }
+
+        match self {
+            NpyData::F32(v) => write(v, out),
+            NpyData::F64(v) => write(v, out),
+            NpyData::Complex64(v) => write(v, out),
+            NpyData::Complex128(v) => write(v, out),
+        }
+    }
+
+    fn permute(&self, indices: &[usize]) -> Self {
+        fn permute<T: Copy>(values: &[T], indices: &[usize]) -> Vec<T> {
+            indices.iter().map(|i| values[*i]).collect()
+        }
+
+        match self {
+            NpyData::F32(v) => NpyData::F32(permute(v, indices)),
+            NpyData::F64(v) => NpyData::F64(permute(v, indices)),
+            NpyData::Complex64(v) => NpyData::Complex64(permute(v, indices)),
+            NpyData::Complex128(v) => NpyData::Complex128(permute(v, indices)),
+        }
+    }
+}
+
+// data is stored in the memory order given by `fortran_order`
+#[derive(Clone, Debug, PartialEq)]
+pub struct NpyArray {
+    pub shape: Vec<usize>,
+    pub fortran_order: bool,
+    pub data: NpyData,
+}
+
+impl From<Vec<f32>> for NpyArray {
+    fn from(values: Vec<f32>) -> Self { Self::new(vec![values.len()], NpyData::F32(values)) }
+}
+
+impl From<Vec<f64>> for NpyArray {
+    fn from(values: Vec<f64>) -> Self { Self::new(vec![values.len()], NpyData::F64(values)) }
+}
+
+impl From<Vec<Complex<f32>>> for NpyArray {
+    fn from(values: Vec<Complex<f32>>) -> Self { Self::new(vec![values.len()], NpyData::Complex64(values)) }
+}
+
+impl From<Vec<Complex<f64>>> for NpyArray {
+    fn from(values: Vec<Complex<f64>>) -> Self { Self::new(vec![values.len()], NpyData::Complex128(values)) }
+}
+
+impl NpyArray {
+    pub fn new(shape: Vec<usize>, data: NpyData) -> Self {
+        assert_eq!(shape.iter().product::<usize>(), data.len(), "shape does not match the number of elements");
+
+        Self {
+            shape,
+            fortran_order: false,
+            data,
+        }
+    }
+
+    pub fn new_fortran(shape: Vec<usize>, data: NpyData) -> Self {
+        Self {
+            fortran_order: true,
+            ..Self::new(shape, data)
+        }
+    }
+
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    pub fn from_reader<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut preamble = [0u8; 8];
+        reader.read_exact(&mut preamble)?;
+        if &preamble[..6] != MAGIC {
+            return Err(invalid_data("not a npy file"));
+        }
+
+        let header_len = match preamble[6] {
+            1 => {
+                let mut len = [0u8; 2];
+                reader.read_exact(&mut len)?;
+                u16::from_le_bytes(len) as usize
+            }
+            2 | 3 => {
+                let mut len = [0u8; 4];
+                reader.read_exact(&mut len)?;
+                u32::from_le_bytes(len) as usize
+            }
+            version => return Err(invalid_data(format!("unsupported npy version {version}"))),
+        };
+
+        let header = read_bytes(&mut reader, header_len)?;
+        let header = String::from_utf8(header).map_err(|_| invalid_data("npy header is not utf8"))?;
+
+        let descr = header_value(&header, "descr")?
+            .trim_matches(|c| c == '\'' || c == '"')
+            .to_string();
+        let fortran_order = match header_value(&header, "fortran_order")? {
+            "True" => true,
+            "False" => false,
+            value => return Err(invalid_data(format!("invalid fortran_order {value}"))),
+        };
+        let shape = header_value(&header, "shape")?
+            .trim_matches(|c| c == '(' || c == ')')
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse().map_err(|_| invalid_data(format!("invalid shape dimension {s}"))))
+            .collect::<io::Result<Vec<usize>>>()?;
+
+        if descr.is_empty() {
+            return Err(invalid_data("npy header has an empty descr"));
+        }
+
+        let (big_endian, kind) = match descr.split_at(1) {
+            ("<" | "|" | "=", kind) => (false, kind),
+            (">", kind) => (true, kind),
+            _ => (false, descr.as_str()),
+        };
+
+        // the shape comes from the file, a corrupt one must not overflow
+        let len = shape.iter()
+            .try_fold(1usize, |len, d| len.checked_mul(*d))
+            .ok_or_else(|| invalid_data(format!("npy shape {shape:?} is too large")))?;
+        let data = match kind {
+            "f4" => NpyData::F32(read_values(&mut reader, len, big_endian)?),
+            "f8" => NpyData::F64(read_values(&mut reader, len, big_endian)?),
+            "c8" => NpyData::Complex64(read_values(&mut reader, len, big_endian)?),
+            "c16" => NpyData::Complex128(read_values(&mut reader, len, big_endian)?),
+            _ => return Err(invalid_data(format!("unsupported dtype {descr}"))),
+        };
+
+        Ok(Self {
+            shape,
+            fortran_order,
+            data,
+        })
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.to_writer(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn to_writer<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let shape = match self.shape.len() {
+            1 => format!("({},)", self.shape[0]),
+            _ => format!("({})", self.shape.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ")),
+        };
+        let mut header = format!(
+            "{{'descr': '{}', 'fortran_order': {}, 'shape': {}, }}",
+            self.data.descr(),
+            if self.fortran_order { "True" } else { "False" },
+            shape,
+        );
+
+        // the data has to start on a 64 bytes boundary
+        let version = if header.len() + 11 < u16::MAX as usize { 1 } else { 2 };
+        let preamble_len = if version == 1 { 10 } else { 12 };
+        let padding = 63 - (preamble_len + header.len()) % 64;
+        header.push_str(&" ".repeat(padding));
+        header.push('\n');
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[version, 0])?;
+        if version == 1 {
+            writer.write_all(&(header.len() as u16).to_le_bytes())?;
+        } else {
+            writer.write_all(&(header.len() as u32).to_le_bytes())?;
+        }
+        writer.write_all(header.as_bytes())?;
+
+        let mut data = vec![];
+        self.data.write_le(&mut data);
+        writer.write_all(&data)
+    }
+
+    pub fn len(&self) -> usize { self.data.len() }
+    pub fn is_empty(&self) -> bool { self.data.is_empty() }
+
+    // same array with its data laid out in C order
+    pub fn to_c_order(&self) -> Self {
+        if !self.fortran_order || self.shape.len() <= 1 {
+            return Self { fortran_order: false, ..self.clone() };
+        }
+
+        let mut strides = vec![1; self.shape.len()];
+        for i in 1..self.shape.len() {
+            strides[i] = strides[i - 1] * self.shape[i - 1];
+        }
+
+        // walks the C ordered indices and look up their fortran offsets
+        let mut index = vec![0; self.shape.len()];
+        let indices = (0..self.len())
+            .map(|_| {
+                let offset = index.iter().zip(&strides).map(|(i, s)| i * s).sum();
+                for axis in (0..index.len()).rev() {
+                    index[axis] += 1;
+                    if index[axis] < self.shape[axis] {
+                        break;
+                    }
+                    index[axis] = 0;
+                }
+                offset
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            shape: self.shape.clone(),
+            fortran_order: false,
+            data: self.data.permute(&indices),
+        }
+    }
+
+    // flattened in C order, real arrays only
+    pub fn to_f64(&self) -> Option<Vec<f64>> {
+        match self.to_c_order().data {
+            NpyData::F32(v) => Some(v.into_iter().map(|v| v as f64).collect()),
+            NpyData::F64(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    // flattened in C order, my_fft input
+    pub fn to_complex(&self) -> Vec<Complex<f64>> {
+        match self.to_c_order().data {
+            NpyData::F32(v) => v.into_iter().map(|v| Complex::new(v as f64, 0.)).collect(),
+            NpyData::F64(v) => v.into_iter().map(|v| Complex::new(v, 0.)).collect(),
+            NpyData::Complex64(v) => v.into_iter().map(|v| Complex::new(v.re as f64, v.im as f64)).collect(),
+            NpyData::Complex128(v) => v,
+        }
+    }
+}
+
+pub fn read_npz<P: AsRef<Path>>(path: P) -> io::Result<BTreeMap<String, NpyArray>> {
+    read_npz_from(BufReader::new(File::open(path)?))
+}
+
+pub fn read_npz_from<R: Read + Seek>(reader: R) -> io::Result<BTreeMap<String, NpyArray>> {
+    let mut archive = ZipArchive::new(reader)?;
+    let mut arrays = BTreeMap::new();
+
+    for i in 0..archive.len() {
+        let file = archive.by_index(i)?;
+        let name = file.name().trim_end_matches(".npy").to_string();
+        arrays.insert(name, NpyArray::from_reader(file)?);
+    }
+
+    Ok(arrays)
+}
+
+// numpy.savez layout, uncompressed
+pub fn write_npz<P: AsRef<Path>>(path: P, arrays: &[(&str, &NpyArray)]) -> io::Result<()> {
+    write_npz_to(BufWriter::new(File::create(path)?), arrays, false)
+}
+
+// numpy.savez_compressed layout
+pub fn write_npz_compressed<P: AsRef<Path>>(path: P, arrays: &[(&str, &NpyArray)]) -> io::Result<()> {
+    write_npz_to(BufWriter::new(File::create(path)?), arrays, true)
+}
+
+pub fn write_npz_to<W: Write + Seek>(writer: W, arrays: &[(&str, &NpyArray)], compressed: bool) -> io::Result<()> {
+    let method = if compressed { CompressionMethod::Deflated } else { CompressionMethod::Stored };
+    let mut zip = ZipWriter::new(writer);
+
+    for (name, array) in arrays {
+        zip.start_file(format!("{name}.npy"), FileOptions::default().compression_method(method))?;
+        array.to_writer(&mut zip)?;
+    }
+
+    zip.finish()?.flush()
+}
+
+fn read_values<T: Element, R: Read>(reader: &mut R, len: usize, big_endian: bool) -> io::Result<Vec<T>> {
+    let size = len
+        .checked_mul(T::size())
+        .ok_or_else(|| invalid_data(format!("npy data of {len} values is too large")))?;
+    let bytes = read_bytes(reader, size)?;
+
+    Ok(bytes
+       .chunks_exact(T::size())
+       .map(|b| T::from_bytes(b, big_endian))
+       .collect())
+}
+
+// the next size bytes, only allocated as they are read since the size comes from the file
+fn read_bytes<R: Read>(reader: &mut R, size: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    reader.take(size as u64).read_to_end(&mut bytes)?;
+    if bytes.len() < size {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("expected {size} bytes, the file ends after {}", bytes.len())));
+    }
+
+    Ok(bytes)
+}
+
+// value of `key` in the python dict literal of the header
+fn header_value<'a>(header: &'a str, key: &str) -> io::Result<&'a str> {
+    let start = header
+        .find(&format!("'{key}'"))
+        .ok_or_else(|| invalid_data(format!("npy header has no {key}")))?;
+    let value = header[start + key.len() + 2..]
+        .trim_start()
+        .strip_prefix(':')
+        .ok_or_else(|| invalid_data("malformed npy header"))?
+        .trim_start();
+
+    let end = if value.starts_with('(') {
+        value.find(')').map(|i| i + 1)
+    } else {
+        value.find([',', '}'])
+    };
+
+    Ok(value[..end.ok_or_else(|| invalid_data("malformed npy header"))?].trim())
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn round_trip(array: &NpyArray) -> NpyArray {
+        let mut bytes = vec![];
+        array.to_writer(&mut bytes).unwrap();
+        assert_eq!(bytes.iter().position(|b| *b == b'\n').unwrap() % 64, 63);
+        NpyArray::from_reader(&bytes[..]).unwrap()
+    }
+
+    #[test]
+    fn round_trip_c_order() {
+        let arrays = [
+            NpyArray::new(vec![2, 3], NpyData::F32(vec![0., 1., 2., 3., 4., 5.])),
+            NpyArray::new(vec![3, 2], NpyData::F64(vec![0.5, -1., 2e10, f64::MIN_POSITIVE, 4., 5.])),
+            NpyArray::new(vec![2], NpyData::Complex64(vec![Complex::new(1., -1.), Complex::new(0., 2.)])),
+            NpyArray::new(vec![1, 2, 1], NpyData::Complex128(vec![Complex::new(1e-300, 3.), Complex::new(-0., 2.)])),
+            NpyArray::new(vec![], NpyData::F64(vec![42.])),
+        ];
+
+        for array in &arrays {
+            assert_eq!(&round_trip(array), array);
+        }
+    }
+
+    #[test]
+    fn round_trip_fortran_order() {
+        // numpy.asfortranarray([[0, 1, 2], [3, 4, 5]])
+        let array = NpyArray::new_fortran(vec![2, 3], NpyData::F64(vec![0., 3., 1., 4., 2., 5.]));
+        let read = round_trip(&array);
+
+        assert_eq!(read, array);
+        assert_eq!(read.to_f64().unwrap(), vec![0., 1., 2., 3., 4., 5.]);
+    }
+
+    #[test]
+    fn fortran_to_c_order_3d() {
+        let shape = vec![2, 3, 4];
+        let c_order: Vec<f64> = (0..24).map(|v| v as f64).collect();
+        let fortran: Vec<f64> = (0..4)
+            .flat_map(|k| (0..3).flat_map(move |j| (0..2).map(move |i| (i * 12 + j * 4 + k) as f64)))
+            .collect();
+
+        let array = NpyArray::new_fortran(shape.clone(), NpyData::F64(fortran));
+        assert_eq!(round_trip(&array).to_c_order(), NpyArray::new(shape, NpyData::F64(c_order)));
+    }
+
+    #[test]
+    fn big_endian_header() {
+        let mut bytes = vec![];
+        NpyArray::from(vec![1f64, 2.]).to_writer(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 16);
+        let descr = bytes.windows(3).position(|w| w == b"<f8").unwrap();
+        bytes[descr] = b'>';
+        bytes.extend(1f64.to_be_bytes());
+        bytes.extend(2f64.to_be_bytes());
+
+        assert_eq!(NpyArray::from_reader(&bytes[..]).unwrap().to_f64().unwrap(), vec![1., 2.]);
+    }
+
+    // version 1 file with the given header dict
+    fn npy_bytes(header: &str, data: &[u8]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend([1, 0]);
+        bytes.extend((header.len() as u16).to_le_bytes());
+        bytes.extend(header.as_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    #[test]
+    fn corrupt_sizes() {
+        let values: Vec<u8> = [1f64, 2.].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let header = |shape: &str| format!("{{'descr': '<f8', 'fortran_order': False, 'shape': {shape}, }}\n");
+        assert_eq!(NpyArray::from_reader(&npy_bytes(&header("(2,)"), &values)[..]).unwrap().len(), 2);
+
+        // a product of the shape overflowing usize
+        let huge = header(&format!("({}, {})", usize::MAX, usize::MAX));
+        let error = NpyArray::from_reader(&npy_bytes(&huge, &values)[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // more values than the file holds, or than bytes can be counted
+        let error = NpyArray::from_reader(&npy_bytes(&header("(1000000000,)"), &values)[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        let error = NpyArray::from_reader(&npy_bytes(&header(&format!("({},)", usize::MAX / 4)), &values)[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // a version 2 header claiming 4 GiB
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend([2, 0]);
+        bytes.extend(u32::MAX.to_le_bytes());
+        bytes.extend(b"{'descr': '<f8'");
+        let error = NpyArray::from_reader(&bytes[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn round_trip_npz() {
+        let x = NpyArray::from(vec![0f32, 1., 2.]);
+        let spectrum = NpyArray::from(vec![Complex::new(1f64, 0.), Complex::new(0., -1.)]);
+
+        for compressed in [false, true] {
+            let mut cursor = Cursor::new(vec![]);
+            write_npz_to(&mut cursor, &[("x", &x), ("spectrum", &spectrum)], compressed).unwrap();
+            cursor.set_position(0);
+
+            let arrays = read_npz_from(cursor).unwrap();
+            assert_eq!(arrays.len(), 2);
+            assert_eq!(arrays["x"], x);
+            assert_eq!(arrays["spectrum"], spectrum);
+        }
+    }
+}
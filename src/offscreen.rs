@@ -0,0 +1,145 @@
+use std::{error::Error, sync::Arc};
+
+use image::RgbaImage;
+use vulkano::{buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer}, device::Device, format::Format, image::{Image, ImageCreateInfo, ImageType, ImageUsage}, memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator}, render_pass::{Framebuffer, RenderPass}};
+
+use crate::window_surface::{create_framebuffer, get_render_pass};
+
+// sRGB like the swapchain images, so the png looks like the window
+pub const OFFSCREEN_FORMAT: Format = Format::R8G8B8A8_SRGB;
+
+// render target that can be read back on the cpu instead of being presented
+pub struct OffscreenTarget {
+    pub image: Arc<Image>,
+    pub render_pass: Arc<RenderPass>,
+    pub framebuffer: Arc<Framebuffer>,
+    pub readback: Subbuffer<[u8]>,
+}
+
+impl OffscreenTarget {
+    // samples must be supported by the device, see `supported_samples`, and the size within its limits,
+    // see `max_extent`
+    pub fn new(device: Arc<Device>, memory_allocator: Arc<StandardMemoryAllocator>, width: u32, height: u32, samples: u32) -> Result<Self, Box<dyn Error>> {
+        let image = Image::new(
+            memory_allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: OFFSCREEN_FORMAT,
+                extent: [width, height, 1],
+                usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+        )?;
+
+        let readback = Buffer::new_slice(
+            memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                ..Default::default()
+            },
+            width as u64 * height as u64 * 4,
+        )?;
+
+        let render_pass = get_render_pass(device, OFFSCREEN_FORMAT, samples);
+        let framebuffer = create_framebuffer(render_pass.clone(), &memory_allocator, image.clone(), samples)?;
+
+        Ok(Self {
+            image,
+            render_pass,
+            framebuffer,
+            readback,
+        })
+    }
+
+    pub fn extent(&self) -> [u32; 2] {
+        let [width, height, _] = self.image.extent();
+        [width, height]
+    }
+
+    // only valid once the copy into the readback buffer has completed
+    pub fn to_image(&self) -> RgbaImage {
+        let [width, height] = self.extent();
+        let pixels = self.readback.read().unwrap().to_vec();
+        RgbaImage::from_raw(width, height, pixels).unwrap()
+    }
+}
+
+// largest width and height of an image the device can render to
+pub fn max_extent(device: &Device) -> u32 {
+    let properties = device.physical_device().properties();
+    properties.max_image_dimension2_d
+        .min(properties.max_framebuffer_width)
+        .min(properties.max_framebuffer_height)
+}
+
+// averages blocks of factor x factor pixels, what is left over on the right and bottom edges is dropped.
+// the colours are averaged in linear space like the blits of the window do on its sRGB images
+pub fn downsample(image: &RgbaImage, factor: u32) -> RgbaImage {
+    if factor <= 1 {
+        return image.clone();
+    }
+
+    let linear: Vec<f32> = (0..=255u8).map(|v| srgb_to_linear(v as f32 / 255.)).collect();
+    let (width, height) = (image.width() / factor, image.height() / factor);
+    let count = (factor * factor) as f32;
+    RgbaImage::from_fn(width, height, |x, y| {
+        let mut sum = [0f32; 4];
+        for j in 0..factor {
+            for i in 0..factor {
+                let pixel = image.get_pixel(x * factor + i, y * factor + j);
+                for (k, (s, c)) in sum.iter_mut().zip(pixel.0).enumerate() {
+                    // alpha is stored linearly
+                    *s += if k < 3 { linear[c as usize] } else { c as f32 / 255. };
+                }
+            }
+        }
+        let [r, g, b, a] = sum.map(|s| s / count);
+        let byte = |v: f32| (v * 255.).round().clamp(0., 255.) as u8;
+        image::Rgba([byte(linear_to_srgb(r)), byte(linear_to_srgb(g)), byte(linear_to_srgb(b)), byte(a)])
+    })
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    match v <= 0.04045 {
+        true => v / 12.92,
+        false => ((v + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    match v <= 0.0031308 {
+        true => v * 12.92,
+        false => 1.055 * v.powf(1. / 2.4) - 0.055,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downsample_in_linear_space() {
+        // black and white columns, half of the light of white once averaged
+        let image = RgbaImage::from_fn(4, 2, |x, _| match x % 2 {
+            0 => image::Rgba([0, 0, 0, 255]),
+            _ => image::Rgba([255, 255, 255, 255]),
+        });
+        let small = downsample(&image, 2);
+
+        assert_eq!((small.width(), small.height()), (2, 1));
+        assert_eq!(small.get_pixel(0, 0).0, [188, 188, 188, 255]);
+
+        // a flat colour stays the same
+        let flat = RgbaImage::from_pixel(4, 4, image::Rgba([10, 120, 240, 128]));
+        assert_eq!(downsample(&flat, 4).get_pixel(0, 0).0, [10, 120, 240, 128]);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{circles::Circle, plot::PlotId};
+
+// a circle found under the cursor
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pick {
+    pub plot: PlotId,
+    // index of the circle in the order they were plotted
+    pub index: usize,
+    // in data coordinates
+    pub position: [f32; 2],
+    // of the series the circle belongs to
+    pub label: Option<String>,
+}
+
+// uniform grid over the circles of a plot in data coordinates, about one circle per cell.
+// scales are monotonic so a box of the axes is still a box of the data
+pub(crate) struct SpatialIndex {
+    // lower corner and size of a cell
+    origin: [f32; 2],
+    cell: [f32; 2],
+    shape: [usize; 2],
+    // the circles of cell i are indices[starts[i]..starts[i + 1]]
+    starts: Vec<usize>,
+    indices: Vec<usize>,
+    // widens the queries, in clip units of the height like the radii
+    pub max_radius: f32,
+}
+
+impl SpatialIndex {
+    pub(crate) fn new(circles: &[Circle]) -> Self {
+        let points: Vec<(usize, [f32; 2])> = circles.iter()
+            .enumerate()
+            .map(|(i, c)| (i, [c.circle_position[0], c.circle_position[1]]))
+            .filter(|(_, [x, y])| x.is_finite() && y.is_finite())
+            .collect();
+        let [min, max] = points.iter().fold([[f32::MAX; 2], [f32::MIN; 2]], |[min, max], (_, p)| [
+            [min[0].min(p[0]), min[1].min(p[1])],
+            [max[0].max(p[0]), max[1].max(p[1])],
+        ]);
+
+        let side = (points.len() as f32).sqrt().ceil().max(1.) as usize;
+        let mut index = Self {
+            origin: if points.is_empty() { [0.; 2] } else { min },
+            cell: [0, 1].map(|i| ((max[i] - min[i]) / side as f32).max(f32::MIN_POSITIVE)),
+            shape: [side, side],
+            starts: vec![0; side * side + 1],
+            indices: vec![0; points.len()],
+            max_radius: circles.iter().map(|c| c.radius).fold(0., f32::max),
+        };
+
+        // counting sort of the circles by cell
+        let cells: Vec<usize> = points.iter().map(|(_, p)| index.cell_index(*p)).collect();
+        for cell in &cells {
+            index.starts[cell + 1] += 1;
+        }
+        for i in 1..index.starts.len() {
+            index.starts[i] += index.starts[i - 1];
+        }
+        let mut next = index.starts.clone();
+        for ((i, _), cell) in points.iter().zip(cells) {
+            index.indices[next[cell]] = *i;
+            next[cell] += 1;
+        }
+
+        index
+    }
+
+    // cell holding a point, the points outside of the grid go to the nearest cell
+    fn cell(&self, point: [f32; 2]) -> [usize; 2] {
+        [0, 1].map(|i| (((point[i] - self.origin[i]) / self.cell[i]).floor().max(0.) as usize).min(self.shape[i] - 1))
+    }
+
+    fn cell_index(&self, point: [f32; 2]) -> usize {
+        let [x, y] = self.cell(point);
+        y * self.shape[0] + x
+    }
+
+    // circles of the cells overlapping [[x0, x1], [y0, y1]], some of them outside of it
+    pub(crate) fn query(&self, [[x0, x1], [y0, y1]]: [[f32; 2]; 2]) -> impl Iterator<Item = usize> + '_ {
+        let ([cx0, cy0], [cx1, cy1]) = (self.cell([x0, y0]), self.cell([x1, y1]));
+        (cy0..=cy1).flat_map(move |cy| (cx0..=cx1).flat_map(move |cx| {
+            let i = cy * self.shape[0] + cx;
+            self.indices[self.starts[i]..self.starts[i + 1]].iter().copied()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(points: &[[f32; 2]]) -> SpatialIndex {
+        let circles: Vec<Circle> = points.iter().map(|[x, y]| Circle::new(0.01, [*x, *y, 0.], [1.; 4])).collect();
+        SpatialIndex::new(&circles)
+    }
+
+    fn query(index: &SpatialIndex, bounds: [[f32; 2]; 2]) -> Vec<usize> {
+        let mut found: Vec<usize> = index.query(bounds).collect();
+        found.sort();
+        found
+    }
+
+    // every point inside the bounds is found
+    fn assert_covers(points: &[[f32; 2]], index: &SpatialIndex, [[x0, x1], [y0, y1]]: [[f32; 2]; 2]) {
+        let found = query(index, [[x0, x1], [y0, y1]]);
+        for (i, [x, y]) in points.iter().enumerate() {
+            if (x0..=x1).contains(x) && (y0..=y1).contains(y) {
+                assert!(found.contains(&i), "{i} at {:?} missing from {found:?}", [x, y]);
+            }
+        }
+    }
+
+    #[test]
+    fn finds_points_in_bounds() {
+        // scattered deterministically over [0; 10]²
+        let points: Vec<[f32; 2]> = (0..200)
+            .map(|i| [(i * 37 % 101) as f32 / 10., (i * 59 % 103) as f32 / 10.])
+            .collect();
+        let index = index(&points);
+
+        for bounds in [[[0., 10.], [0., 10.]], [[2., 3.], [5., 7.5]], [[9.9, 20.], [-5., 0.1]], [[4.5, 4.5], [4.5, 4.5]]] {
+            assert_covers(&points, &index, bounds);
+        }
+        // the grid keeps the queries local
+        assert!(query(&index, [[2., 3.], [2., 3.]]).len() < points.len() / 4);
+    }
+
+    #[test]
+    fn single_point() {
+        let index = index(&[[3., -2.]]);
+        assert_eq!(query(&index, [[2.9, 3.1], [-2.1, -1.9]]), vec![0]);
+        // one cell holds it whatever the query
+        assert_eq!(query(&index, [[100., 200.], [100., 200.]]), vec![0]);
+    }
+
+    #[test]
+    fn collinear_points() {
+        let horizontal: Vec<[f32; 2]> = (0..50).map(|i| [i as f32, 1.]).collect();
+        let index_h = index(&horizontal);
+        assert_covers(&horizontal, &index_h, [[10., 12.], [0.5, 1.5]]);
+        assert!(query(&index_h, [[10., 12.], [0.5, 1.5]]).len() < horizontal.len());
+
+        let vertical: Vec<[f32; 2]> = (0..50).map(|i| [-4., i as f32 * 0.1]).collect();
+        let index_v = index(&vertical);
+        assert_covers(&vertical, &index_v, [[-5., -3.], [2., 2.2]]);
+
+        let same: Vec<[f32; 2]> = vec![[1., 1.]; 10];
+        assert_eq!(query(&index(&same), [[0., 2.], [0., 2.]]), (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn skips_non_finite() {
+        let points = [[0., 0.], [f32::NAN, 1.], [1., f32::INFINITY], [2., 2.]];
+        let grid = index(&points);
+
+        assert_eq!(query(&grid, [[-1., 3.], [-1., 3.]]), vec![0, 3]);
+        // a query with NaN bounds falls back to the edge cells instead of panicking
+        let _ = query(&grid, [[f32::NAN, f32::NAN], [0., 1.]]);
+        assert!(query(&index(&[[f32::NAN, 0.]]), [[-1., 1.], [-1., 1.]]).is_empty());
+    }
+}
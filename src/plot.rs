@@ -1,37 +1,218 @@
-use std::sync::Arc;
+use std::{cell::Cell, sync::atomic::{AtomicU32, Ordering}};
 
-use glam::Vec3;
-use vulkano::{device::Device, instance::Instance, memory::allocator::StandardMemoryAllocator};
-use winit::{event_loop::EventLoop, window::WindowId};
+use glam::{Mat4, Vec3};
 
-use crate::{camera::Camera, circles::Circle, window_surface::WindowSurface};
+use crate::{axes::{Axes, Axis, TickLabel}, camera::Camera, circles::Circle, colorbar::Colorbar, heatmap::Heatmap, legend::{Legend, LegendPosition, LegendShapes, Series, Swatch}, lines::{Cap, Segment}, picking::{Pick, SpatialIndex}, rectangles::Rectangle, scale::Scale, text::{self, Align, Text}, ticks};
 
-pub struct Plot {
-    pub window_surface : WindowSurface,
+static NEXT_PLOT_ID: AtomicU32 = AtomicU32::new(0);
+
+// pixels between the cursor and the edge of a circle it still picks
+const PICK_TOLERANCE: f32 = 4.;
+
+// identifies a plot whether it is shown in a window or rendered offscreen
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PlotId(u32);
+
+impl PlotId {
+    fn next() -> Self {
+        PlotId(NEXT_PLOT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+// bounds of the data in axis coordinates, see `Plot::axis_bounds`
+#[derive(Clone, Copy, Debug)]
+struct Bounds {
+    generation: u64,
+    // circles, lines, rectangles and heatmaps the bounds were taken from
+    counts: [usize; 4],
+    // None along an axis without unmasked values
+    bounds: [Option<[f32; 2]>; 2],
+}
+
+// what the overlay of a plot is laid out from, it is only built again when this changes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct OverlayKey {
+    generation: u64,
+    overlay_generation: u64,
+    // the data a legend looking for the best place avoids
+    counts: Option<[usize; 4]>,
+    limits: [[f32; 2]; 2],
+    region: [[u32; 2]; 2],
+}
+
+// everything drawn over the data of a plot, in clip space with y up. built at once so the legend is laid out once
+#[derive(Default)]
+pub(crate) struct Overlay {
+    pub rectangles: Vec<Rectangle>,
     pub circles: Vec<Circle>,
+    pub segments: Vec<Segment>,
+    pub texts: Vec<Text>,
+    pub heatmaps: Vec<Heatmap>,
+}
+
+// what a manager draws in one pass, the overlay of a plot goes over all of its data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Layer {
+    // through the limits of the axes
+    Data,
+    // axes, labels, colorbar and legend, in clip space with y up
+    Overlay,
+}
+
+// the figure, only data, drawn in the windows and images the plotter attaches to it
+pub struct Plot {
+    id: PlotId,
+    // plot owning the grid this one is a cell of
+    pub(crate) host: Option<PlotId>,
+    // cells of the grid, row major, the host first
+    pub(crate) subplots: Vec<PlotId>,
+    // [[x0, x1], [y0, y1]] fractions of the window covered by the plot, y pointing down
+    region: [[f32; 2]; 2],
+    pub(crate) circles: Vec<Circle>,
+    pub(crate) lines: Vec<Segment>,
+    pub(crate) rectangles: Vec<Rectangle>,
+    pub(crate) heatmaps: Vec<Heatmap>,
+    colorbar: Option<Colorbar>,
+    // in the order they were plotted, the labelled ones are listed by the legend
+    series: Vec<Series>,
+    legend: Option<Legend>,
+    // first circle of each group of markers and the series it belongs to
+    circle_series: Vec<(usize, usize)>,
+    // built on the first pick, dropped when circles are added
+    circle_index: Option<SpatialIndex>,
+    // circle under the cursor, shown in the overlay
+    tooltip: Option<Pick>,
+    // anchored in data coordinates
+    pub(crate) texts: Vec<Text>,
+    title: Option<String>,
+    xlabel: Option<String>,
+    ylabel: Option<String>,
     pub camera: Camera,
+    pub axes: Axes,
+    // size of the window or image the plot is drawn in, kept in sync with the window by the plotter
+    extent: [u32; 2],
+    // explicit axis limits, autoscaled from the data when None
+    xlim: Option<[f32; 2]>,
+    ylim: Option<[f32; 2]>,
+    // limits before panning and zooming, restored by `reset_view`
+    home: Option<[Option<[f32; 2]>; 2]>,
+    // box being dragged for a zoom, corners in pixels of the canvas
+    selection: Option<[[f32; 2]; 2]>,
+    // fraction of the data range added on each side when autoscaling
+    margin: f32,
+    // bumped when the data changes other than by appending, the buffers of the plot are then rebuilt
+    generation: u64,
+    // bumped when the explicit limits change, only what is drawn from the masked edges is rebuilt
+    limits_generation: u64,
+    // bumped when the titles, the legend, the colorbar, the tooltip or the zoom box change,
+    // along with the limits and the size they give the overlay
+    overlay_generation: u64,
+    // kept along with the generation, extended by what is appended
+    bounds: Cell<Option<Bounds>>,
+    // multisampling, samples per pixel
+    samples: u32,
+    // rendered at this multiple of the size then downsampled
+    supersampling: u32,
 }
 
 impl Plot {
-    pub fn new(instance : Arc<Instance>, device : Arc<Device>, event_loop : &EventLoop<()>) -> Self {
-        let window_surface = WindowSurface::new(instance.clone(), device.clone(), event_loop);
-        let mut camera = Camera::new();
-        camera.set_position(Vec3::new(0., 0., -1.));
-        camera.set_rotation(Vec3::NEG_Z);
+    pub(crate) fn new() -> Self {
+        let camera = Camera::new();
 
         Self {
-            window_surface,
+            id: PlotId::next(),
+            host: None,
+            subplots: vec![],
+            region: [[0., 1.], [0., 1.]],
             camera,
-            circles: vec![]
+            axes: Axes::default(),
+            circles: vec![],
+            lines: vec![],
+            rectangles: vec![],
+            heatmaps: vec![],
+            colorbar: None,
+            series: vec![],
+            legend: None,
+            circle_series: vec![],
+            circle_index: None,
+            tooltip: None,
+            texts: vec![],
+            title: None,
+            xlabel: None,
+            ylabel: None,
+            extent: [800, 600],
+            xlim: None,
+            ylim: None,
+            home: None,
+            selection: None,
+            margin: 0.05,
+            generation: 0,
+            limits_generation: 0,
+            overlay_generation: 0,
+            bounds: Cell::new(None),
+            samples: 1,
+            supersampling: 1,
         }
     }
 
-    pub fn id(&self) -> WindowId {
-        self.window_surface.id()
+    pub fn id(&self) -> PlotId {
+        self.id
     }
 
-    pub fn scatter(&mut self, data : &mut Vec<Circle>) -> &mut Self {
+    pub(crate) fn scatter(&mut self, data : &mut Vec<Circle>) -> &mut Self {
         self.circles.append(data);
+        self.circle_index = None;
+        self
+    }
+
+    // circles listed as one series, picking gives them its label
+    pub(crate) fn add_markers(&mut self, data : &mut Vec<Circle>, series: Series) -> &mut Self {
+        self.circle_series.push((self.circles.len(), self.series.len()));
+        self.scatter(data)
+            .add_series(series.swatch, series.label)
+    }
+
+    pub(crate) fn plot(&mut self, data : &mut Vec<Segment>) -> &mut Self {
+        self.lines.append(data);
+        self
+    }
+
+    pub(crate) fn rectangles(&mut self, data : &mut Vec<Rectangle>) -> &mut Self {
+        self.rectangles.append(data);
+        self
+    }
+
+    pub(crate) fn heatmap(&mut self, heatmap: Heatmap) -> &mut Self {
+        self.heatmaps.push(heatmap);
+        self
+    }
+
+    pub fn colorbar(&mut self, colorbar: Colorbar) -> &mut Self {
+        self.colorbar = Some(colorbar);
+        self.overlay_generation += 1;
+        self
+    }
+
+    pub fn annotate(&mut self, text: Text) -> &mut Self {
+        self.texts.push(text);
+        self
+    }
+
+    pub fn title(&mut self, title: impl Into<String>) -> &mut Self {
+        self.title = Some(title.into());
+        self.overlay_generation += 1;
+        self
+    }
+
+    pub fn xlabel(&mut self, label: impl Into<String>) -> &mut Self {
+        self.xlabel = Some(label.into());
+        self.overlay_generation += 1;
+        self
+    }
+
+    pub fn ylabel(&mut self, label: impl Into<String>) -> &mut Self {
+        self.ylabel = Some(label.into());
+        self.overlay_generation += 1;
         self
     }
 
@@ -42,15 +223,784 @@ impl Plot {
 
     pub fn clear(&mut self) -> &mut Self {
         self.circles.clear();
+        self.lines.clear();
+        self.rectangles.clear();
+        self.heatmaps.clear();
+        self.colorbar = None;
+        self.series.clear();
+        self.legend = None;
+        self.circle_series.clear();
+        self.circle_index = None;
+        self.tooltip = None;
+        self.texts.clear();
+        self.generation += 1;
+        self
+    }
+
+    pub(crate) fn add_series(&mut self, swatch: Swatch, label: Option<String>) -> &mut Self {
+        self.series.push(Series { label, swatch });
+        self.overlay_generation += 1;
+        self
+    }
+
+    // labels the last series plotted
+    pub fn label(&mut self, label: impl Into<String>) -> &mut Self {
+        if let Some(series) = self.series.last_mut() {
+            series.label = Some(label.into());
+        }
+        self.overlay_generation += 1;
+        self
+    }
+
+    pub fn legend(&mut self, position: LegendPosition) -> &mut Self {
+        self.legend = Some(Legend::new(position));
+        self.overlay_generation += 1;
+        self
+    }
+
+    pub fn set_legend(&mut self, legend: Legend) -> &mut Self {
+        self.legend = Some(legend);
+        self.overlay_generation += 1;
+        self
+    }
+
+    pub fn xlim(&mut self, min: f32, max: f32) -> &mut Self {
+        assert!(valid_limits([min, max]), "x limits must be finite and distinct, got [{min}, {max}]");
+        self.xlim = Some([min, max]);
+        self.home = None;
+        self.limits_generation += 1;
+        self
+    }
+
+    pub fn ylim(&mut self, min: f32, max: f32) -> &mut Self {
+        assert!(valid_limits([min, max]), "y limits must be finite and distinct, got [{min}, {max}]");
+        self.ylim = Some([min, max]);
+        self.home = None;
+        self.limits_generation += 1;
+        self
+    }
+
+    // forget explicit limits, fit the data again
+    pub fn autoscale(&mut self) -> &mut Self {
+        self.xlim = None;
+        self.ylim = None;
+        self.home = None;
+        self.limits_generation += 1;
+        self
+    }
+
+    // whether a point of the canvas, in pixels with y down, is in the region of the plot
+    pub fn contains(&self, [x, y]: [f32; 2]) -> bool {
+        let [[x0, x1], [y0, y1]] = self.pixel_region().map(|r| r.map(|v| v as f32));
+        (x0..x1).contains(&x) && (y0..y1).contains(&y)
+    }
+
+    // a point of the canvas in pixels with y down, to clip space of the plot with y up
+    fn canvas_to_clip(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        let ([ox, oy], [w, h]) = (self.offset(), self.extent());
+        [2. * (x - ox as f32) / w as f32 - 1., 1. - 2. * (y - oy as f32) / h as f32]
+    }
+
+    // a point of the canvas in pixels with y down, to axis coordinates
+    pub fn canvas_to_axis(&self, point: [f32; 2]) -> [f32; 2] {
+        let clip = self.canvas_to_clip(point);
+        let (limits, frame) = (self.axis_limits(), self.frame());
+        [0, 1].map(|i| {
+            let ([min, max], [a, b]) = (limits[i], frame[i]);
+            min + (clip[i] - a) / (b - a) * (max - min)
+        })
+    }
+
+    // axis coordinates to a point of the canvas in pixels with y down
+    pub fn axis_to_canvas(&self, point: [f32; 2]) -> [f32; 2] {
+        let (limits, frame) = (self.axis_limits(), self.frame());
+        let [x, y] = [0, 1].map(|i| {
+            let ([min, max], [a, b]) = (limits[i], frame[i]);
+            a + (point[i] - min) / (max - min) * (b - a)
+        });
+        let ([ox, oy], [w, h]) = (self.offset(), self.extent());
+        [ox as f32 + 0.5 * (x + 1.) * w as f32, oy as f32 + 0.5 * (1. - y) * h as f32]
+    }
+
+    // the circle under a point of the canvas in pixels, the nearest one when several are within reach
+    pub fn pick(&mut self, cursor: [f32; 2]) -> Option<Pick> {
+        if self.circles.is_empty() || !self.contains(cursor) {
+            return None;
+        }
+        if self.circle_index.is_none() {
+            self.circle_index = Some(SpatialIndex::new(&self.circles));
+        }
+        let index = self.circle_index.as_ref().unwrap();
+
+        // box around the cursor, in data coordinates
+        let height = self.extent()[1] as f32;
+        let reach = index.max_radius * 0.5 * height + PICK_TOLERANCE;
+        let [a, b] = [-reach, reach].map(|d| self.canvas_to_axis([cursor[0] + d, cursor[1] + d]));
+        let [xscale, yscale] = self.axes.scales;
+        let bounds = [
+            [xscale.inverse(a[0]), xscale.inverse(b[0])],
+            [yscale.inverse(a[1]), yscale.inverse(b[1])],
+        ].map(|[u, v]| [u.min(v), u.max(v)]);
+
+        // distance to the edge, negative inside
+        let distance = |circle: &Circle| {
+            let [x, y] = self.axis_to_canvas(self.to_axis([circle.circle_position[0], circle.circle_position[1]])?);
+            Some((x - cursor[0]).hypot(y - cursor[1]) - circle.radius * 0.5 * height)
+        };
+
+        index.query(bounds)
+            .filter_map(|i| Some((i, distance(&self.circles[i])?)))
+            .filter(|(_, d)| *d <= PICK_TOLERANCE)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| Pick {
+                plot: self.id,
+                index: i,
+                position: [self.circles[i].circle_position[0], self.circles[i].circle_position[1]],
+                label: self.circle_label(i),
+            })
+    }
+
+    fn circle_label(&self, index: usize) -> Option<String> {
+        let group = self.circle_series.partition_point(|(start, _)| *start <= index);
+        let (_, series) = self.circle_series.get(group.checked_sub(1)?)?;
+        self.series[*series].label.clone()
+    }
+
+    // shows the circle under the cursor, None hides it. true when the tooltip changed
+    pub(crate) fn hover(&mut self, cursor: Option<[f32; 2]>) -> bool {
+        let tooltip = cursor.and_then(|cursor| self.pick(cursor));
+        let changed = tooltip != self.tooltip;
+        self.tooltip = tooltip;
+        if changed {
+            self.overlay_generation += 1;
+        }
+        changed
+    }
+
+    // the label and coordinates of the hovered circle next to it, in clip space with y up
+    fn tooltip_shapes(&self) -> Option<(Rectangle, Text)> {
+        let pick = self.tooltip.as_ref()?;
+        let circle = &self.circles[pick.index];
+        let center = self.canvas_to_clip(self.axis_to_canvas(self.to_axis(pick.position)?));
+
+        let value = |v: f32| ticks::format(v as f64, (v.abs() as f64 * 1e-3).max(1e-9));
+        let coordinates = format!("({}, {})", value(pick.position[0]), value(pick.position[1]));
+        let string = match &pick.label {
+            Some(label) => format!("{label}: {coordinates}"),
+            None => coordinates,
+        };
+
+        let (size, padding) = (12., 4.);
+        let pixel = self.pixel();
+        let width = (text::text_width(&string, size) + 2. * padding) * pixel[0];
+        let height = (size + 2. * padding) * pixel[1];
+        let gap = circle.radius * self.extent()[1] as f32 * 0.5 * pixel[0] + padding * pixel[0];
+        // on the left of the circle when it would leave the plot on the right
+        let left = match center[0] + gap + width > 1. {
+            true => center[0] - gap - width,
+            false => center[0] + gap,
+        };
+        let bottom = (center[1] - 0.5 * height).clamp(-1., 1. - height);
+
+        let background = Rectangle::from_corners([left, bottom], [left + width, bottom + height], [0.1, 0.1, 0.1, 0.8]);
+        let text = Text::new([left + padding * pixel[0], bottom + 0.5 * height], string)
+            .align(Align::Start, Align::Center)
+            .size(size)
+            .color(self.axes.color);
+        Some((background, text))
+    }
+
+    // explicit limits given in axis coordinates, the first change saves the view to go back to
+    fn set_axis_limits(&mut self, limits: [[f32; 2]; 2]) -> &mut Self {
+        if !limits.iter().all(|limits| valid_limits(*limits)) {
+            return self;
+        }
+
+        self.home.get_or_insert([self.xlim, self.ylim]);
+        let [xscale, yscale] = self.axes.scales;
+        let [[x0, x1], [y0, y1]] = limits;
+        self.xlim = Some([xscale.inverse(x0), xscale.inverse(x1)]);
+        self.ylim = Some([yscale.inverse(y0), yscale.inverse(y1)]);
         self
     }
 
-    pub fn height(&self) -> u32 { self.window_surface.inner_size().height }
-    pub fn width(&self) -> u32 { self.window_surface.inner_size().width }
+    // moves the data along with the cursor, points in pixels of the canvas
+    pub fn pan(&mut self, from: [f32; 2], to: [f32; 2]) -> &mut Self {
+        let ([ax, ay], [bx, by]) = (self.canvas_to_axis(from), self.canvas_to_axis(to));
+        let [[x0, x1], [y0, y1]] = self.axis_limits();
+        let [dx, dy] = [bx - ax, by - ay];
+        self.set_axis_limits([[x0 - dx, x1 - dx], [y0 - dy, y1 - dy]])
+    }
+
+    // scales the limits around a point of the canvas, below 1 zooms in
+    pub fn zoom(&mut self, center: [f32; 2], factor: f32) -> &mut Self {
+        let center = self.canvas_to_axis(center);
+        let limits = self.axis_limits();
+        self.set_axis_limits([0, 1].map(|i| limits[i].map(|v| center[i] + (v - center[i]) * factor)))
+    }
+
+    // shows the box between two points of the canvas, a box of a few pixels is ignored
+    pub fn zoom_to(&mut self, a: [f32; 2], b: [f32; 2]) -> &mut Self {
+        if (a[0] - b[0]).abs() < 3. || (a[1] - b[1]).abs() < 3. {
+            return self;
+        }
+
+        let (a, b) = (self.canvas_to_axis(a), self.canvas_to_axis(b));
+        let limits = self.axis_limits();
+        // keeps the direction of inverted limits
+        self.set_axis_limits([0, 1].map(|i| {
+            let (low, high) = (a[i].min(b[i]), a[i].max(b[i]));
+            match limits[i][0] <= limits[i][1] {
+                true => [low, high],
+                false => [high, low],
+            }
+        }))
+    }
+
+    // back to the limits before panning and zooming
+    pub fn reset_view(&mut self) -> &mut Self {
+        if let Some([xlim, ylim]) = self.home.take() {
+            self.xlim = xlim;
+            self.ylim = ylim;
+        }
+        self
+    }
+
+    pub(crate) fn set_selection(&mut self, selection: Option<[[f32; 2]; 2]>) -> &mut Self {
+        self.selection = selection;
+        self.overlay_generation += 1;
+        self
+    }
+
+    pub fn set_margin(&mut self, margin: f32) -> &mut Self {
+        self.margin = margin;
+        self
+    }
+
+    pub fn xscale(&mut self, scale: Scale) -> &mut Self {
+        self.axes.scales[0] = scale;
+        self.generation += 1;
+        self
+    }
+
+    pub fn yscale(&mut self, scale: Scale) -> &mut Self {
+        self.axes.scales[1] = scale;
+        self.generation += 1;
+        self
+    }
+
+    // data to axis coordinates, None when a scale masks the point
+    pub fn to_axis(&self, [x, y]: [f32; 2]) -> Option<[f32; 2]> {
+        let [xscale, yscale] = self.axes.scales;
+        Some([xscale.forward(x)?, yscale.forward(y)?])
+    }
+
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    // for changes made through the public fields, like the axes
+    pub(crate) fn overlay_changed(&mut self) -> &mut Self {
+        self.overlay_generation += 1;
+        self
+    }
+
+    pub(crate) fn overlay_key(&self) -> OverlayKey {
+        let best = self.legend.as_ref().is_some_and(|legend| legend.position == LegendPosition::Best);
+        OverlayKey {
+            generation: self.generation,
+            overlay_generation: self.overlay_generation,
+            counts: best.then_some([self.circles.len(), self.lines.len(), self.rectangles.len(), self.heatmaps.len()]),
+            limits: self.axis_limits(),
+            region: self.pixel_region(),
+        }
+    }
+
+    // changes along with the masked edges, both counters only grow so their sum changes whenever one does
+    pub(crate) fn masked_generation(&self) -> u64 {
+        self.generation + self.limits_generation
+    }
+
+    // an interval in axis coordinates, a masked end is clamped to the lower limit
+    fn to_axis_interval(&self, axis: usize, [a, b]: [f32; 2], lower: f32) -> Option<[f32; 2]> {
+        let scale = self.axes.scales[axis];
+        match (scale.forward(a), scale.forward(b)) {
+            (Some(a), Some(b)) => Some([a, b]),
+            (Some(a), None) => Some([a, lower]),
+            (None, Some(b)) => Some([lower, b]),
+            (None, None) => None,
+        }
+    }
+
+    // the data from the start-th item on in axis coordinates, masked points left out
+    pub(crate) fn scaled_circles(&self, start: usize) -> Vec<Circle> {
+        self.circles[start..].iter()
+            .filter_map(|c| {
+                let [x, y] = self.to_axis([c.circle_position[0], c.circle_position[1]])?;
+                Some(Circle { circle_position: [x, y, c.circle_position[2]], ..c.clone() })
+            })
+            .collect()
+    }
+
+    pub(crate) fn scaled_lines(&self, start: usize) -> Vec<Segment> {
+        self.lines[start..].iter()
+            .filter_map(|s| {
+                let [x0, y0] = self.to_axis([s.start[0], s.start[1]])?;
+                let [x1, y1] = self.to_axis([s.end[0], s.end[1]])?;
+                // a masked neighbour leaves the join without one
+                let [xp, yp] = self.to_axis([s.previous[0], s.previous[1]]).unwrap_or([x0, y0]);
+                let [xn, yn] = self.to_axis([s.next[0], s.next[1]]).unwrap_or([x1, y1]);
+                Some(Segment {
+                    start: [x0, y0, s.start[2]],
+                    end: [x1, y1, s.end[2]],
+                    previous: [xp, yp, s.previous[2]],
+                    next: [xn, yn, s.next[2]],
+                    ..s.clone()
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) fn scaled_rectangles(&self, start: usize) -> Vec<Rectangle> {
+        let [x_lower, y_lower] = self.masked_edges();
+        self.rectangles[start..].iter()
+            .filter_map(|r| {
+                let ([x, y, z], [w, h]) = (r.position, r.size);
+                let [x0, x1] = self.to_axis_interval(0, [x, x + w], x_lower)?;
+                let [y0, y1] = self.to_axis_interval(1, [y, y + h], y_lower)?;
+                Some(Rectangle { position: [x0, y0, z], size: [x1 - x0, y1 - y0], ..r.clone() })
+            })
+            .collect()
+    }
+
+    // only the corners are mapped, the cells stay evenly spread between them
+    pub(crate) fn scaled_heatmaps(&self, start: usize) -> Vec<Heatmap> {
+        let [x_lower, y_lower] = self.masked_edges();
+        self.heatmaps[start..].iter()
+            .filter_map(|h| {
+                let extent = [
+                    self.to_axis_interval(0, h.extent[0], x_lower)?,
+                    self.to_axis_interval(1, h.extent[1], y_lower)?,
+                ];
+                Some(Heatmap { extent, ..h.clone() })
+            })
+            .collect()
+    }
+
+    pub(crate) fn scaled_texts(&self, start: usize) -> Vec<Text> {
+        self.texts[start..].iter()
+            .filter_map(|t| Some(Text { position: self.to_axis(t.position)?, ..t.clone() }))
+            .collect()
+    }
+
+    // [[xmin, xmax], [ymin, ymax]] of everything drawn, None without data
+    pub fn data_bounds(&self) -> Option<[[f32; 2]; 2]> {
+        let [xscale, yscale] = self.axes.scales;
+        self.axis_bounds().map(|[[x0, x1], [y0, y1]]| [
+            [xscale.inverse(x0), xscale.inverse(x1)],
+            [yscale.inverse(y0), yscale.inverse(y1)],
+        ])
+    }
+
+    // bounds in axis coordinates, the masked values are left out.
+    // only the items appended since the last call are scanned while the generation stays the same
+    fn axis_bounds(&self) -> Option<[[f32; 2]; 2]> {
+        let counts = [self.circles.len(), self.lines.len(), self.rectangles.len(), self.heatmaps.len()];
+        let (start, bounds) = match self.bounds.get() {
+            Some(cached) if cached.generation == self.generation && cached.counts.iter().zip(counts).all(|(a, b)| *a <= b) => {
+                (cached.counts, cached.bounds)
+            }
+            _ => ([0; 4], [None; 2]),
+        };
+
+        let bounds = [0, 1].map(|axis| {
+            let scale = self.axes.scales[axis];
+            self.coordinates(axis, start)
+                .filter_map(|v| scale.forward(v))
+                .fold(bounds[axis], |range, v| match range {
+                    None => Some([v, v]),
+                    Some([min, max]) => Some([min.min(v), max.max(v)]),
+                })
+        });
+        self.bounds.set(Some(Bounds { generation: self.generation, counts, bounds }));
+
+        let [x, y] = bounds;
+        Some([x?, y?])
+    }
+
+    // data coordinates along an axis of the items from the given circle, line, rectangle and heatmap on
+    fn coordinates(&self, axis: usize, [circles, lines, rectangles, heatmaps]: [usize; 4]) -> impl Iterator<Item = f32> + '_ {
+        self.circles[circles..].iter().map(move |c| c.circle_position[axis])
+            .chain(self.lines[lines..].iter().flat_map(move |l| [l.start[axis], l.end[axis]]))
+            .chain(self.rectangles[rectangles..].iter().flat_map(move |r| [r.position[axis], r.position[axis] + r.size[axis]]))
+            .chain(self.heatmaps[heatmaps..].iter().flat_map(move |h| h.extent[axis]))
+    }
+
+    // displayed [[xmin, xmax], [ymin, ymax]]
+    pub fn limits(&self) -> [[f32; 2]; 2] {
+        let [[x0, x1], [y0, y1]] = self.axis_limits();
+        let [xscale, yscale] = self.axes.scales;
+
+        [[xscale.inverse(x0), xscale.inverse(x1)], [yscale.inverse(y0), yscale.inverse(y1)]]
+    }
+
+    // displayed limits in axis coordinates, an explicit limit the scale masks is autoscaled
+    pub(crate) fn axis_limits(&self) -> [[f32; 2]; 2] {
+        self.limits_of([self.xlim, self.ylim])
+    }
+
+    // the given explicit limits in axis coordinates, the others fit the data
+    fn limits_of(&self, lims: [Option<[f32; 2]>; 2]) -> [[f32; 2]; 2] {
+        let bounds = self.axis_bounds();
+        let autoscale = |axis: usize| match bounds {
+            None => [-1., 1.],
+            Some(bounds) => {
+                let [min, max] = bounds[axis];
+                if max > min {
+                    let margin = (max - min) * self.margin;
+                    [min - margin, max + margin]
+                } else {
+                    let margin = if min == 0. { 0.5 } else { min.abs() * 0.05 };
+                    [min - margin, max + margin]
+                }
+            }
+        };
+
+        [0, 1].map(|axis| {
+            let scale = self.axes.scales[axis];
+            match lims[axis].map(|[min, max]| (scale.forward(min), scale.forward(max))) {
+                Some((Some(min), Some(max))) => [min, max],
+                Some((min, max)) => {
+                    let [auto_min, auto_max] = autoscale(axis);
+                    [min.unwrap_or(auto_min), max.unwrap_or(auto_max)]
+                }
+                None => autoscale(axis),
+            }
+        })
+    }
+
+    // where the edges a scale masks are drawn from, the bottom of the limits before panning and zooming.
+    // kept out of the view for the data buffers to stay valid while navigating
+    fn masked_edges(&self) -> [f32; 2] {
+        self.limits_of(self.home.unwrap_or([self.xlim, self.ylim]))
+            .map(|[a, b]| a.min(b))
+    }
+
+    // maps the limits onto the axes frame with y pointing up, vulkan's y axis points down
+    pub fn data_transform(&self) -> Mat4 {
+        let [[x0, x1], [y0, y1]] = self.axis_limits();
+        let [[left, right], [bottom, top]] = self.frame();
+        let scale = Vec3::new((right - left) / (x1 - x0), -(top - bottom) / (y1 - y0), 1.);
+        let translation = Vec3::new(left - x0 * scale.x, -bottom - y0 * scale.y, 0.);
+
+        Mat4::from_translation(translation) * Mat4::from_scale(scale)
+    }
+
+    // the axes are given in clip space with y up
+    pub(crate) fn overlay_transform(&self) -> Mat4 {
+        Mat4::from_scale(Vec3::new(1., -1., 1.))
+    }
+
+    pub(crate) fn transform(&self, layer: Layer) -> Mat4 {
+        match layer {
+            Layer::Data => self.data_transform(),
+            Layer::Overlay => self.overlay_transform(),
+        }
+    }
+
+    // positions of the data in clip space with y up, what the legend avoids
+    fn overlay_points(&self) -> Vec<[f32; 2]> {
+        let transform = self.overlay_transform() * self.data_transform();
+        let to_overlay = |[x, y]: [f32; 2]| {
+            let p = transform.transform_point3(Vec3::new(x, y, 0.));
+            [p.x, p.y]
+        };
+
+        let circles = self.scaled_circles(0).into_iter().map(|c| [c.circle_position[0], c.circle_position[1]]);
+        let lines = self.scaled_lines(0).into_iter().flat_map(|s| {
+            let [a, b] = [s.start, s.end].map(|p| [p[0], p[1]]);
+            [a, [0.5 * (a[0] + b[0]), 0.5 * (a[1] + b[1])], b]
+        });
+        let rectangles = self.scaled_rectangles(0).into_iter().flat_map(|r| {
+            let ([x, y, _], [w, h]) = (r.position, r.size);
+            [[x, y], [x + w, y], [x, y + h], [x + w, y + h], [x + 0.5 * w, y + 0.5 * h]]
+        });
+
+        circles.chain(lines).chain(rectangles).map(to_overlay).collect()
+    }
+
+    // the data is only gone through when the legend looks for the best place
+    fn legend_shapes(&self) -> LegendShapes {
+        match &self.legend {
+            Some(legend) if legend.position == LegendPosition::Best => {
+                legend.shapes(&self.series, self.frame(), self.pixel(), &self.overlay_points())
+            }
+            Some(legend) => legend.shapes(&self.series, self.frame(), self.pixel(), &[]),
+            None => LegendShapes::default(),
+        }
+    }
+
+    // axes, labels, colorbar, legend, tooltip and zoom box
+    pub(crate) fn overlay(&self) -> Overlay {
+        let legend = self.legend_shapes();
+
+        let mut rectangles = legend.rectangles;
+        let mut texts = self.label_texts();
+        texts.extend(legend.texts);
+        if let Some((background, text)) = self.tooltip_shapes() {
+            rectangles.push(background);
+            texts.push(text);
+        }
+
+        let mut segments = self.axes_segments();
+        segments.extend(legend.segments);
+        segments.extend(self.selection_segments());
+
+        Overlay {
+            rectangles,
+            circles: legend.circles,
+            segments,
+            texts,
+            heatmaps: self.overlay_heatmaps(),
+        }
+    }
+
+    // size of a pixel in clip space
+    fn pixel(&self) -> [f32; 2] {
+        self.extent().map(|v| 2. / v.max(1) as f32)
+    }
+
+    // [[left, right], [bottom, top]] of the axes box in clip space with y up, room is left for the colorbar
+    pub fn frame(&self) -> [[f32; 2]; 2] {
+        let mut frame = self.axes.frame(self.extent());
+        if let Some(colorbar) = &self.colorbar {
+            frame[0][1] -= colorbar.space() * self.pixel()[0];
+        }
+
+        frame
+    }
+
+    // spines, ticks and the frame of the colorbar
+    fn axes_segments(&self) -> Vec<Segment> {
+        let mut segments = self.axes.segments(self.axis_limits(), self.frame(), self.extent());
+        if let Some(colorbar) = &self.colorbar {
+            segments.extend(colorbar.segments(self.frame(), self.pixel(), &self.axes));
+        }
+
+        segments
+    }
+
+    // the box being dragged for a zoom
+    fn selection_segments(&self) -> Vec<Segment> {
+        let Some(selection) = self.selection else { return vec![] };
+        let [[x0, y0], [x1, y1]] = selection.map(|p| self.canvas_to_clip(p));
+        Segment::polyline(
+            &[[x0, y0, 0.], [x1, y0, 0.], [x1, y1, 0.], [x0, y1, 0.], [x0, y0, 0.]],
+            1.,
+            self.axes.color,
+            Cap::Square,
+        )
+    }
+
+    // the colorbar gradient, in clip space with y up
+    fn overlay_heatmaps(&self) -> Vec<Heatmap> {
+        self.colorbar
+            .iter()
+            .map(|colorbar| colorbar.heatmap(self.frame(), self.pixel()))
+            .collect()
+    }
+
+    pub fn tick_labels(&self) -> Vec<TickLabel> {
+        self.axes.tick_labels(self.axis_limits(), self.frame(), self.extent())
+    }
+
+    // tick labels, title and axis labels in clip space with y up
+    fn label_texts(&self) -> Vec<Text> {
+        let pixel = self.pixel();
+        let color = self.axes.color;
+        let gap = 3.;
+
+        let mut texts: Vec<Text> = self.tick_labels()
+            .into_iter()
+            .map(|label| match label.axis {
+                Axis::X => Text::new([label.position[0], label.position[1] - gap * pixel[1]], label.text)
+                    .align(Align::Center, Align::End),
+                Axis::Y => Text::new([label.position[0] - gap * pixel[0], label.position[1]], label.text)
+                    .align(Align::End, Align::Center),
+            }
+            .size(12.)
+            .color(color))
+            .collect();
+
+        // against the borders of the window, outside of the tick labels
+        let [[x0, x1], [y0, y1]] = self.frame();
+        if let Some(colorbar) = &self.colorbar {
+            texts.extend(colorbar.texts(self.frame(), pixel, &self.axes));
+        }
+        if let Some(title) = &self.title {
+            texts.push(Text::new([0.5 * (x0 + x1), 1. - 2. * gap * pixel[1]], title.clone())
+                .align(Align::Center, Align::End)
+                .size(16.)
+                .color(color));
+        }
+        if let Some(xlabel) = &self.xlabel {
+            texts.push(Text::new([0.5 * (x0 + x1), -1. + gap * pixel[1]], xlabel.clone())
+                .align(Align::Center, Align::Start)
+                .color(color));
+        }
+        if let Some(ylabel) = &self.ylabel {
+            texts.push(Text::new([-1. + gap * pixel[0], 0.5 * (y0 + y1)], ylabel.clone())
+                .align(Align::Center, Align::End)
+                .rotation(std::f32::consts::FRAC_PI_2)
+                .color(color));
+        }
+
+        texts
+    }
+
+    // size of the whole window or image the plot is drawn in
+    pub fn canvas(&self) -> [u32; 2] {
+        self.extent
+    }
+
+    // pixel bounds of the region, neighbouring cells share their edges
+    fn pixel_region(&self) -> [[u32; 2]; 2] {
+        let canvas = self.canvas();
+        [0, 1].map(|i| self.region[i].map(|v| (v * canvas[i] as f32).round() as u32))
+    }
+
+    // size of the region of the canvas the plot is drawn in
+    pub fn extent(&self) -> [u32; 2] {
+        self.pixel_region().map(|[start, end]| end.saturating_sub(start).max(1))
+    }
+
+    // top left corner of the region, in pixels
+    pub fn offset(&self) -> [u32; 2] {
+        self.pixel_region().map(|[start, _]| start)
+    }
+
+    pub(crate) fn set_region(&mut self, region: [[f32; 2]; 2]) -> &mut Self {
+        self.region = region;
+        // the cell can change shape while the canvas keeps its size
+        self.camera.update_aspect_ratio(self.aspect());
+        self
+    }
+
+    // 1, 2, 4 or 8, clamped to what the device supports when rendering
+    pub fn set_samples(&mut self, samples: u32) -> &mut Self {
+        self.samples = samples.clamp(1, 8);
+        self
+    }
+
+    // 1, 2 or 4, rounded down to a power of two
+    pub fn set_supersampling(&mut self, factor: u32) -> &mut Self {
+        self.supersampling = 1 << factor.clamp(1, 4).ilog2();
+        self
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn supersampling(&self) -> u32 {
+        self.supersampling
+    }
+
+    pub(crate) fn set_extent(&mut self, extent: [u32; 2]) -> &mut Self {
+        self.extent = extent;
+        self.camera.update_aspect_ratio(self.aspect());
+        self
+    }
+
+    pub fn height(&self) -> u32 { self.extent()[1] }
+    pub fn width(&self) -> u32 { self.extent()[0] }
     pub fn aspect(&self) -> f32 { self.width() as f32 / self.height() as f32 }
 
     pub fn update_camera(&mut self, fov: f32, znear: f32, zfar: f32) -> &mut Self {
         self.camera.set_perspective(fov, self.aspect(), znear, zfar);
         self
     }
+
+    // the plot seen through a lens of the given field of view in degrees, flat again with `orthographic`
+    pub fn perspective(&mut self, fov: f32) -> &mut Self {
+        self.camera.set_fov(fov);
+        self
+    }
+
+    pub fn orthographic(&mut self) -> &mut Self {
+        self.camera.set_orthographic();
+        self
+    }
+
+    // tilts the plot back by the elevation then turns it by the azimuth, in degrees.
+    // panning, zooming and picking still follow the plot as seen from the front
+    pub fn view_angles(&mut self, elevation: f32, azimuth: f32) -> &mut Self {
+        self.camera.set_rotation(Vec3::new(-elevation, azimuth, 0.));
+        self
+    }
+}
+
+// limits an axis can be mapped from without dividing by zero
+fn valid_limits([min, max]: [f32; 2]) -> bool {
+    min.is_finite() && max.is_finite() && min != max
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circles(points: &[[f32; 2]]) -> Vec<Circle> {
+        points.iter().map(|[x, y]| Circle::new(0.01, [*x, *y, 0.], [1.; 4])).collect()
+    }
+
+    #[test]
+    fn bounds_follow_the_data() {
+        let mut plot = Plot::new();
+        assert_eq!(plot.data_bounds(), None);
+
+        plot.scatter(&mut circles(&[[0., 1.], [2., -1.]]));
+        assert_eq!(plot.data_bounds(), Some([[0., 2.], [-1., 1.]]));
+
+        // appended data extends the cached bounds
+        plot.scatter(&mut circles(&[[-3., 0.5]]));
+        plot.plot(&mut vec![Segment::new([1., 1., 0.], [1., 4., 0.], 1., [1.; 4])]);
+        assert_eq!(plot.data_bounds(), Some([[-3., 2.], [-1., 4.]]));
+
+        // a new scale masks the non positive values
+        plot.yscale(Scale::Log10);
+        let [_, [y0, y1]] = plot.data_bounds().unwrap();
+        assert!((y0 - 0.5).abs() < 1e-6 && (y1 - 4.).abs() < 1e-5);
+
+        plot.yscale(Scale::Linear).clear();
+        assert_eq!(plot.data_bounds(), None);
+        plot.scatter(&mut circles(&[[5., 6.]]));
+        assert_eq!(plot.data_bounds(), Some([[5., 5.], [6., 6.]]));
+    }
+
+    #[test]
+    fn overlay_key_follows_what_the_overlay_shows() {
+        let mut plot = Plot::new();
+        plot.xlim(0., 1.).ylim(0., 1.);
+        let key = plot.overlay_key();
+
+        // data within explicit limits leaves the overlay alone, unless the legend avoids it
+        plot.scatter(&mut circles(&[[0.5, 0.5]]));
+        assert_eq!(plot.overlay_key(), key);
+        plot.legend(LegendPosition::Best);
+        let key = plot.overlay_key();
+        plot.scatter(&mut circles(&[[0.2, 0.2]]));
+        assert_ne!(plot.overlay_key(), key);
+
+        let key = plot.overlay_key();
+        plot.pan([0., 0.], [10., 10.]);
+        assert_ne!(plot.overlay_key(), key);
+
+        let key = plot.overlay_key();
+        plot.title("title");
+        assert_ne!(plot.overlay_key(), key);
+
+        let key = plot.overlay_key();
+        plot.set_region([[0., 0.5], [0., 1.]]);
+        assert_ne!(plot.overlay_key(), key);
+    }
 }
@@ -1,18 +1,52 @@
 use core::panic;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, error::Error, path::Path, sync::{mpsc::{self, Receiver, Sender}, Arc}};
 
-use vulkano::{command_buffer::{allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents}, device::{physical::{PhysicalDevice, PhysicalDeviceType}, Device, DeviceCreateInfo, DeviceExtensions, Queue, QueueCreateInfo, QueueFlags}, instance::{Instance, InstanceCreateInfo}, memory::allocator::StandardMemoryAllocator, pipeline::graphics::viewport::Viewport, render_pass::{Framebuffer, RenderPass}, swapchain::{self, Surface, SwapchainPresentInfo}, sync::{future::FenceSignalFuture, GpuFuture}, Validated, VulkanError};
+use vulkano::{command_buffer::{allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents}, device::{physical::{PhysicalDevice, PhysicalDeviceType}, Device, DeviceCreateInfo, DeviceExtensions, Queue, QueueCreateInfo, QueueFlags}, instance::{Instance, InstanceCreateInfo}, memory::allocator::StandardMemoryAllocator, render_pass::{Framebuffer, RenderPass}, swapchain::{self, Surface, SwapchainPresentInfo}, sync::{future::FenceSignalFuture, GpuFuture}, Validated, VulkanError};
 use winit::{event::{Event, WindowEvent, KeyboardInput}, platform::run_return::EventLoopExtRunReturn, window::{WindowBuilder, WindowId}};
-use winit::event_loop::{ControlFlow, EventLoop};
-use crate::{circles::Circle, plot::Plot, renderer::Renderer};
+use winit::event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget};
+use image::RgbaImage;
+use crate::{colorbar::Colorbar, colormap::Colormap, heatmap::Heatmap, lines::{Cap, Segment}, legend::{LegendPosition, Swatch}, markers::{Marker, Markers}, picking::Pick, plot::{Plot, PlotId}, rectangles::{Bars, Rectangle}, renderer::Renderer, scale::Scale, text::Text, window_surface::WindowSurface};
+
+type PickCallback = Box<dyn FnMut(&Pick)>;
+
+// change sent by a `PlotHandle`, run on the thread of the event loop
+pub type Update = Box<dyn FnOnce(&mut Plotter) + Send>;
+
+// sends changes to a plotter from other threads, they are applied between two frames of its windows
+#[derive(Clone)]
+pub struct PlotHandle {
+    sender: Sender<Update>,
+}
+
+impl PlotHandle {
+    // false once the plotter is dropped. the updates only run inside `show` and `update`,
+    // a headless plotter never runs them
+    pub fn update(&self, update: impl FnOnce(&mut Plotter) + Send + 'static) -> bool {
+        self.sender.send(Box::new(update)).is_ok()
+    }
+}
 
 pub struct Plotter {
     instance : Arc<Instance>,
     device : Arc<Device>,
-    event_loop : EventLoop<()>,
-    plots : HashMap<WindowId, Plot>,
+    // None when rendering offscreen only
+    event_loop : Option<EventLoop<()>>,
+    plots : HashMap<PlotId, Plot>,
+    // windows attached to the figures, by the plot owning the grid
+    windows : HashMap<PlotId, WindowSurface>,
     queue:  Arc<Queue>,
-    current_plot: WindowId,
+    current_plot: PlotId,
+    // called with the circle clicked in a window
+    on_pick: Option<PickCallback>,
+    // changes pushed by the handles, see `handle`
+    sender: Sender<Update>,
+    receiver: Receiver<Update>,
+    // data changed since the buffers were built
+    stale: bool,
+    // inside `show` or `update`, the event loop is lent to them
+    running: bool,
+    // figures attached by updates while running, their windows are opened before the next frame
+    pending_windows: Vec<PlotId>,
     // memory_allocator : Arc<StandardMemoryAllocator>,
     renderer: Renderer
 }
@@ -40,57 +74,176 @@ impl Plotter {
             ..DeviceExtensions::empty()
         };
 
-        let (physical_device, queue_family_index) = select_physical_device(&instance, &surface, &device_extensions);
-        let (device, mut queues) = Device::new(
-            physical_device.clone(),
-            DeviceCreateInfo {
-                queue_create_infos: vec![QueueCreateInfo {
-                    queue_family_index,
-                    ..Default::default()
-                }],
-                enabled_extensions: device_extensions,
-                ..Default::default()
-            }
-            )
-            .expect("failed to create device");
-
-        let queue = queues.next().unwrap();
-        let memory_allocator = Arc::new(StandardMemoryAllocator::new_default(device.clone()));
-        // let descriptor_set_allocator = StandardDescriptorSetAllocator::new(device.clone(), Default::default());
-        let renderer = Renderer::new(device.clone(), memory_allocator.clone(), queue.clone());
+        let (physical_device, queue_family_index) = select_physical_device(&instance, Some(&surface), &device_extensions);
+        let (device, queue, renderer) = create_device(physical_device, queue_family_index, device_extensions);
 
         println!("{:?}", device.physical_device().properties().framebuffer_color_sample_counts & device.physical_device().properties().framebuffer_depth_sample_counts);
 
-        let plot = Plot::new(instance.clone(), device.clone(), &event_loop);
+        let plot = Plot::new();
         let current_plot = plot.id();
         let mut plots = HashMap::new();
         plots.insert(current_plot, plot);
+        let (sender, receiver) = mpsc::channel();
+
+        let mut windows = HashMap::new();
+        windows.insert(current_plot, WindowSurface::new(instance.clone(), device.clone(), &event_loop));
+        sync_canvases(&mut plots, &windows);
 
         Self {
             instance,
             device,
-            event_loop,
+            event_loop: Some(event_loop),
             plots,
+            windows,
             queue,
             current_plot,
+            on_pick: None,
+            sender,
+            receiver,
+            stale: true,
+            running: false,
+            pending_windows: vec![],
             // memory_allocator,
             renderer
         }
     }
 
-    pub fn current_plot(&mut self) -> &mut Plot {
+    // no window nor swapchain, plots can only be rendered with `save_png` / `render_image`.
+    // works without a display, on a software driver like lavapipe.
+    pub fn headless() -> Self {
+        let library = vulkano::VulkanLibrary::new().expect("no local vulkan library/DLL");
+        let instance = Instance::new(library, InstanceCreateInfo::default())
+            .expect("failed to create instance");
+
+        let device_extensions = DeviceExtensions::empty();
+        let (physical_device, queue_family_index) = select_physical_device(&instance, None, &device_extensions);
+        let (device, queue, renderer) = create_device(physical_device, queue_family_index, device_extensions);
+
+        let plot = Plot::new();
+        let current_plot = plot.id();
+        let mut plots = HashMap::new();
+        plots.insert(current_plot, plot);
+        let (sender, receiver) = mpsc::channel();
+
+        Self {
+            instance,
+            device,
+            event_loop: None,
+            plots,
+            windows: HashMap::new(),
+            queue,
+            current_plot,
+            on_pick: None,
+            sender,
+            receiver,
+            stale: true,
+            running: false,
+            pending_windows: vec![],
+            renderer
+        }
+    }
+
+    pub fn current_plot(&self) -> &Plot {
+        &self.plots[&self.current_plot]
+    }
+
+    // what is changed through it, the public fields included, is drawn from the next frame on
+    pub fn current_plot_mut(&mut self) -> &mut Plot {
+        self.changed_plot().overlay_changed()
+    }
+
+    // the current plot, for the methods changing it. its buffers are synced before the next frame
+    fn changed_plot(&mut self) -> &mut Plot {
+        self.stale = true;
         self.plots.get_mut(&self.current_plot).unwrap()
     }
 
+    // for other threads to change the plots while the windows are shown, see `PlotHandle::update`
+    pub fn handle(&self) -> PlotHandle {
+        PlotHandle { sender: self.sender.clone() }
+    }
+
     pub fn scatter(&mut self, x : Vec<f32>, y : Vec<f32>, radius : f32, color : [f32; 4]) -> &mut Self {
-        let mut circles = x
+        self.markers(Markers::new(x, y, radius, color))
+    }
+
+    // outline width in pixels, drawn inside the radius
+    pub fn scatter_with_outline(&mut self, x : Vec<f32>, y : Vec<f32>, radius : f32, color : [f32; 4], outline_color : [f32; 4], outline_width : f32) -> &mut Self {
+        self.markers(Markers::new(x, y, radius, color).outline(outline_color, outline_width))
+    }
+
+    pub fn scatter_with_marker(&mut self, x : Vec<f32>, y : Vec<f32>, radius : f32, color : [f32; 4], marker : Marker) -> &mut Self {
+        self.markers(Markers::new(x, y, radius, color).marker(marker))
+    }
+
+    // matplotlib's `s=` and `c=` with rgba colours
+    pub fn scatter_colors(&mut self, x : Vec<f32>, y : Vec<f32>, sizes : Vec<f32>, colors : Vec<[f32; 4]>) -> &mut Self {
+        let radius = sizes.first().copied().unwrap_or(0.01);
+        self.markers(Markers::new(x, y, radius, [1., 1., 1., 1.]).sizes(sizes).colors(colors))
+    }
+
+    // matplotlib's `s=` and `c=` with values, range is [vmin, vmax], the range of the values when None
+    pub fn scatter_values(&mut self, x : Vec<f32>, y : Vec<f32>, sizes : Vec<f32>, values : Vec<f32>, colormap : Colormap, range : Option<[f32; 2]>) -> &mut Self {
+        let radius = sizes.first().copied().unwrap_or(0.01);
+        self.markers(Markers::new(x, y, radius, [1., 1., 1., 1.]).sizes(sizes).values(values, colormap, range))
+    }
+
+    // scatter with every option, see `Markers`
+    pub fn markers(&mut self, markers: Markers) -> &mut Self {
+        let mut circles = markers.circles();
+        let series = markers.series();
+
+        self.changed_plot()
+            .add_markers(&mut circles, series);
+        self
+    }
+
+    // connected line through the points, width in pixels.
+    // non finite values split the line.
+    pub fn plot(&mut self, x : Vec<f32>, y : Vec<f32>, width : f32, color : [f32; 4]) -> &mut Self {
+        self.plot_with_cap(x, y, width, color, Cap::Butt)
+    }
+
+    pub fn plot_with_cap(&mut self, x : Vec<f32>, y : Vec<f32>, width : f32, color : [f32; 4], cap : Cap) -> &mut Self {
+        let points: Vec<[f32; 3]> = x
             .iter()
             .zip(y.iter())
-            .map(|(a, b)| Circle::new(radius, [*a, *b, 0.], color))
+            .map(|(a, b)| [*a, *b, 0.])
+            .collect();
+
+        let mut segments = points
+            .split(|p| !p[0].is_finite() || !p[1].is_finite())
+            .flat_map(|points| Segment::polyline(points, width, color, cap))
             .collect();
 
-        self.current_plot()
-            .scatter(&mut circles);
+        self.changed_plot()
+            .plot(&mut segments)
+            .add_series(Swatch::Line { color, width }, None);
+        self
+    }
+
+    // vertical bars centered on x, from 0 to height
+    pub fn bar(&mut self, x : Vec<f32>, height : Vec<f32>, width : f32, color : [f32; 4]) -> &mut Self {
+        self.bars(Bars::new(x, height, color).width(width))
+    }
+
+    // horizontal bars centered on y, from 0 to width
+    pub fn barh(&mut self, y : Vec<f32>, width : Vec<f32>, height : f32, color : [f32; 4]) -> &mut Self {
+        self.bars(Bars::new(y, width, color).width(height).horizontal())
+    }
+
+    // grouped or stacked series, custom baseline
+    pub fn bars(&mut self, bars : Bars) -> &mut Self {
+        for series in bars.legend_series() {
+            self.changed_plot().add_series(series.swatch, series.label);
+        }
+        self.rectangles(bars.rectangles())
+    }
+
+    pub fn rectangles(&mut self, rectangles : Vec<Rectangle>) -> &mut Self {
+        let mut rectangles = rectangles;
+        self.changed_plot()
+            .rectangles(&mut rectangles);
         self
     }
 
@@ -99,19 +252,227 @@ impl Plotter {
         for plot in self.plots.values_mut() {
             plot.clear();
         }
+        self.stale = true;
 
         self
     }
 
-    pub fn new_plot(&mut self) -> &mut Self {
-        let plot = Plot::new(
-            self.instance.clone(),
-            self.device.clone(),
-            &self.event_loop,
-            );
+    // grid of values coloured through a colormap
+    pub fn heatmap(&mut self, heatmap: Heatmap) -> &mut Self {
+        self.changed_plot()
+            .heatmap(heatmap);
+
+        self
+    }
+
+    pub fn colorbar(&mut self, colorbar: Colorbar) -> &mut Self {
+        self.changed_plot()
+            .colorbar(colorbar);
+
+        self
+    }
+
+    // string anchored at (x, y) in data coordinates
+    pub fn text(&mut self, x: f32, y: f32, string: &str) -> &mut Self {
+        self.changed_plot()
+            .annotate(Text::new([x, y], string));
+
+        self
+    }
+
+    // labels the last series of the current plot, listed by its legend
+    pub fn label(&mut self, label: &str) -> &mut Self {
+        self.changed_plot().label(label);
+        self
+    }
 
+    pub fn legend(&mut self, position: LegendPosition) -> &mut Self {
+        self.changed_plot().legend(position);
+        self
+    }
+
+    // axis limits of the current plot, in data coordinates
+    pub fn xlim(&mut self, min: f32, max: f32) -> &mut Self {
+        self.changed_plot().xlim(min, max);
+        self
+    }
+
+    pub fn ylim(&mut self, min: f32, max: f32) -> &mut Self {
+        self.changed_plot().ylim(min, max);
+        self
+    }
+
+    pub fn autoscale(&mut self) -> &mut Self {
+        self.changed_plot().autoscale();
+        self
+    }
+
+    pub fn xscale(&mut self, scale: Scale) -> &mut Self {
+        self.changed_plot().xscale(scale);
+        self
+    }
+
+    pub fn yscale(&mut self, scale: Scale) -> &mut Self {
+        self.changed_plot().yscale(scale);
+        self
+    }
+
+    pub fn loglog(&mut self) -> &mut Self {
+        self.xscale(Scale::Log10).yscale(Scale::Log10)
+    }
+
+    pub fn semilogx(&mut self) -> &mut Self {
+        self.xscale(Scale::Log10).yscale(Scale::Linear)
+    }
+
+    pub fn semilogy(&mut self) -> &mut Self {
+        self.xscale(Scale::Linear).yscale(Scale::Log10)
+    }
+
+    pub fn perspective(&mut self, fov: f32) -> &mut Self {
+        self.changed_plot().perspective(fov);
+        self
+    }
+
+    pub fn orthographic(&mut self) -> &mut Self {
+        self.changed_plot().orthographic();
+        self
+    }
+
+    pub fn view_angles(&mut self, elevation: f32, azimuth: f32) -> &mut Self {
+        self.changed_plot().view_angles(elevation, azimuth);
+        self
+    }
+
+    // hovering a circle shows its coordinates, clicking it calls back with them
+    pub fn on_pick(&mut self, callback: impl FnMut(&Pick) + 'static) -> &mut Self {
+        self.on_pick = Some(Box::new(callback));
+        self
+    }
+
+    // multisample anti-aliasing of the current figure, see `Plot::set_samples`
+    pub fn samples(&mut self, samples: u32) -> &mut Self {
+        let host = self.host();
+        self.plots.get_mut(&host).unwrap().set_samples(samples);
+        self.update_antialiasing(host)
+    }
+
+    // renders the current figure at a multiple of its size then downsamples, see `Plot::set_supersampling`
+    pub fn supersampling(&mut self, factor: u32) -> &mut Self {
+        let host = self.host();
+        self.plots.get_mut(&host).unwrap().set_supersampling(factor);
+        self.update_antialiasing(host)
+    }
+
+    fn update_antialiasing(&mut self, host: PlotId) -> &mut Self {
+        if let Some(window_surface) = self.windows.get_mut(&host) {
+            let plot = &self.plots[&host];
+            window_surface.set_antialiasing(plot.samples(), plot.supersampling());
+            // recorded for the previous render pass and framebuffers
+            self.renderer.remove_command_buffers(window_surface.id());
+        }
+        self
+    }
+
+    // opens a window showing the figure of the current plot, unless it already has one
+    pub fn attach_window(&mut self) -> &mut Self {
+        let host = self.host();
+        if self.windows.contains_key(&host) || self.pending_windows.contains(&host) {
+            return self;
+        }
+
+        match self.event_loop.take() {
+            Some(event_loop) => {
+                self.open_window(host, &event_loop);
+                self.event_loop = Some(event_loop);
+            }
+            // an update of a handle, the loop running it opens the window
+            None if self.running => self.pending_windows.push(host),
+            None => panic!("a headless plotter can not open windows, use save_png instead"),
+        }
+        self
+    }
+
+    fn open_window(&mut self, host: PlotId, event_loop: &EventLoopWindowTarget<()>) {
+        let window_surface = WindowSurface::new(self.instance.clone(), self.device.clone(), event_loop);
+        self.windows.insert(host, window_surface);
+        self.update_antialiasing(host);
+        sync_canvases(&mut self.plots, &self.windows);
+    }
+
+    // closes the window of the current figure, its data is kept and can still be rendered to images
+    pub fn detach_window(&mut self) -> &mut Self {
+        if let Some(window_surface) = self.windows.remove(&self.host()) {
+            self.renderer.remove_command_buffers(window_surface.id());
+            window_surface.window.set_visible(false);
+        }
+        self
+    }
+
+    // plot owning the grid the current plot is a cell of
+    fn host(&self) -> PlotId {
+        self.plots[&self.current_plot].host.unwrap_or(self.current_plot)
+    }
+
+    // splits the window of the current plot into a grid of axes, each with its own data, limits and camera.
+    // the top left cell keeps the window and becomes the current plot, the others are selected with `subplot`
+    pub fn subplots(&mut self, rows: usize, cols: usize) -> &mut Self {
+        assert!(rows > 0 && cols > 0, "a grid needs at least one row and one column");
+        let host = self.host();
+
+        let previous = std::mem::take(&mut self.plots.get_mut(&host).unwrap().subplots);
+        for id in previous.iter().filter(|id| **id != host) {
+            self.plots.remove(id);
+        }
+
+        let canvas = self.plots[&host].canvas();
+        let mut subplots = vec![host];
+        for _ in 1..rows * cols {
+            let mut plot = Plot::new();
+            plot.host = Some(host);
+            plot.set_extent(canvas);
+            subplots.push(plot.id());
+            self.plots.insert(plot.id(), plot);
+        }
+
+        for (i, id) in subplots.iter().enumerate() {
+            let (row, col) = (i / cols, i % cols);
+            self.plots.get_mut(id).unwrap().set_region([
+                [col as f32 / cols as f32, (col + 1) as f32 / cols as f32],
+                [row as f32 / rows as f32, (row + 1) as f32 / rows as f32],
+            ]);
+        }
+
+        self.plots.get_mut(&host).unwrap().subplots = subplots;
+        self.current_plot = host;
+        self.stale = true;
+        // the window draws other plots
+        if let Some(window_surface) = self.windows.get(&host) {
+            self.renderer.remove_command_buffers(window_surface.id());
+        }
+        self
+    }
+
+    // selects the i-th axes of the grid, row major from the top left, for the following calls
+    pub fn subplot(&mut self, index: usize) -> &mut Self {
+        let host = self.host();
+        self.current_plot = match figure_ids(&self.plots, host).get(index) {
+            Some(id) => *id,
+            None => panic!("no subplot {index}, the grid has {} cells", figure_ids(&self.plots, host).len()),
+        };
+        self
+    }
+
+    // a new figure, in its own window unless the plotter is headless
+    pub fn new_plot(&mut self) -> &mut Self {
+        let plot = Plot::new();
         self.current_plot = plot.id();
         self.plots.insert(plot.id(), plot);
+        self.stale = true;
+
+        if self.event_loop.is_some() || self.running {
+            self.attach_window();
+        }
         self
     }
 
@@ -119,33 +480,85 @@ impl Plotter {
         // for plot in self.plots.values_mut() {
         //     plot.create_buffer();
         // }
-        self.renderer.create_buffer(self.plots.values());
+        sync_canvases(&mut self.plots, &self.windows);
+        self.renderer.create_buffer(self.plots.values(), self.windows.values());
+        self.stale = false;
 
         self
     }
 
+    // a detached figure gets a new window. blocks until every window is closed, see `update` to keep control.
+    // from an update of a handle it only attaches the window, the loop already running shows it
     pub fn show(&mut self) -> &mut Self {
-        self.create_buffers();
-        self.main_loop();
+        if self.running {
+            return self.attach_window();
+        }
+        if self.windows.is_empty() {
+            self.attach_window();
+        }
+        self.stale = true;
+        self.main_loop(false);
         self.clear()
     }
 
-    fn main_loop(&mut self)  -> &mut Self{
-        let mut viewports = HashMap::new();
-        for plot in self.plots.values_mut() {
-            let viewport = Viewport {
-                offset: [0., 0.],
-                extent: plot.window_surface.inner_size().into(),
-                depth_range: 0.0..=1.0,
-            };
-
-            plot.camera.update_view_matrix();
-            self.renderer.build_command_buffers(&plot, viewport.clone());
-            viewports.insert(plot.id(), viewport);
+    // handles the pending events and draws one frame without blocking, to plot from a loop of the caller.
+    // false once every window is closed
+    pub fn update(&mut self) -> bool {
+        if self.windows.is_empty() {
+            return false;
         }
+        self.main_loop(true);
+        !self.windows.is_empty()
+    }
 
+    // new data gets new buffers, the windows whose command buffers were dropped get new ones:
+    // new windows and the ones drawing buffers or framebuffers that changed since they were recorded
+    fn refresh(&mut self) {
+        if self.stale {
+            self.create_buffers();
+        }
+        for (id, window_surface) in &self.windows {
+            if self.renderer.get_command_buffer(window_surface.id()).is_none() {
+                self.renderer.build_command_buffers(window_surface, &figure_plots(&self.plots, *id));
+            }
+        }
+    }
+
+    // renders the current figure offscreen, whether it is shown in a window or not.
+    // fails when the size is beyond what the device supports
+    pub fn render_image(&mut self, width: u32, height: u32) -> Result<RgbaImage, Box<dyn Error>> {
+        let host = self.host();
+        // the axes depend on the size of the image rather than on the one of the window
+        for id in figure_ids(&self.plots, host) {
+            let plot = self.plots.get_mut(&id).unwrap();
+            plot.set_extent([width, height]);
+        }
+        self.renderer.create_buffer(self.plots.values(), self.windows.values());
 
-        self.event_loop.run_return(|event, _, control_flow| match event {
+        let image = self.renderer.render_offscreen(&figure_plots(&self.plots, host), width, height);
+        if self.windows.contains_key(&host) {
+            self.create_buffers();
+        }
+        image
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&mut self, path: P, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+        self.render_image(width, height)?
+            .save_with_format(path, image::ImageFormat::Png)?;
+        Ok(())
+    }
+
+    // once: returns after one frame instead of when every window is closed
+    fn main_loop(&mut self, once: bool)  -> &mut Self{
+        // taken for the updates to get the whole plotter, windows they attach are opened through the target
+        let mut event_loop = self.event_loop
+            .take()
+            .expect("a headless plotter can not be shown, use save_png instead");
+        self.running = true;
+
+        self.refresh();
+
+        event_loop.run_return(|event, target, control_flow| match event {
             Event::WindowEvent {
                 window_id,
                 event: WindowEvent::CloseRequested,
@@ -153,12 +566,16 @@ impl Plotter {
             } => {
                 println!("removed : {:?}", window_id);
 
-                match self.plots.remove_entry(&window_id) {
-                    Some((_key, plot)) => plot.window_surface.window.set_visible(false),
+                // the figure is kept, only its window goes away
+                match find_window(&self.windows, window_id).and_then(|id| self.windows.remove(&id)) {
+                    Some(window_surface) => {
+                        self.renderer.remove_command_buffers(window_id);
+                        window_surface.window.set_visible(false)
+                    }
                     None => println!("try to close window that does not exist")
                 }
 
-                if self.plots.len() == 0 {
+                if self.windows.is_empty() {
                     *control_flow = ControlFlow::Exit;
                 }
             }
@@ -166,26 +583,62 @@ impl Plotter {
                 window_id,
                 event: WindowEvent::Resized(_),
                 ..
-            } => self.plots.get_mut(&window_id).unwrap().window_surface.window_resized = true,
+            } => {
+                if let Some(id) = find_window(&self.windows, window_id) {
+                    self.windows.get_mut(&id).unwrap().window_resized = true;
+                    // tick lengths and paddings are in pixels
+                    sync_canvases(&mut self.plots, &self.windows);
+                    self.renderer.update_overlays(self.plots.values());
+                }
+            }
+            // panning, zooming and picking
+            Event::WindowEvent { window_id, event } => {
+                if let Some(id) = find_window(&self.windows, window_id) {
+                    let figure = figure_ids(&self.plots, id);
+                    let window_surface = self.windows.get_mut(&id).unwrap();
+                    let response = window_surface.navigation.handle(&event, &mut self.plots, &figure);
+                    window_surface.view_changed |= response.redraw;
+                    if let (Some(pick), Some(on_pick)) = (response.pick, self.on_pick.as_mut()) {
+                        on_pick(&pick);
+                    }
+                }
+            }
             // Event::RedrawRequested(window_id) => println!("window_id : {:?}", window_id),
             Event::MainEventsCleared => {
-                for plot in self.plots.values_mut() {
-
-                    let viewport = viewports.get_mut(&plot.id()).unwrap();
+                if once {
+                    *control_flow = ControlFlow::Exit;
+                }
 
-                    if plot.window_surface.recreate_swapchain || plot.window_surface.window_resized {
-                        plot.window_surface.recreate_swapchain();
-                        plot.update_camera(160., 0., 1.);
+                // data pushed from other threads
+                let updates: Vec<Update> = self.receiver.try_iter().collect();
+                for update in updates {
+                    update(self);
+                }
+                for host in std::mem::take(&mut self.pending_windows) {
+                    self.open_window(host, target);
+                }
 
-                        if plot.window_surface.window_resized {
-                            plot.window_surface.window_resized = false;
-                            viewport.extent = plot.window_surface.inner_size().into();
-                            self.renderer.build_command_buffers(&plot, viewport.clone());
-                        }
+                let mut view_changed = false;
+                for window_surface in self.windows.values_mut() {
+                    // the command buffers draw into the framebuffers of the old swapchain
+                    if window_surface.recreate_swapchain || window_surface.window_resized {
+                        window_surface.recreate_swapchain();
+                        window_surface.window_resized = false;
+                        self.renderer.remove_command_buffers(window_surface.id());
                     }
+                    view_changed |= std::mem::take(&mut window_surface.view_changed);
+                }
+                // the limits only change the overlay and the transforms, the data buffers stay
+                if view_changed {
+                    self.renderer.update_overlays(self.plots.values());
+                }
+                self.refresh();
 
+                let ids: Vec<PlotId> = self.windows.keys().copied().collect();
+                for id in ids {
+                    let window_surface = self.windows.get_mut(&id).unwrap();
                     let (image_i, suboptimal, acquire_future) =
-                        match plot.window_surface.acquire_next_image().map_err(Validated::unwrap)
+                        match window_surface.acquire_next_image().map_err(Validated::unwrap)
                         {
                             Ok(r) => r,
                             Err(VulkanError::OutOfDate) => return,
@@ -193,15 +646,15 @@ impl Plotter {
                         };
 
                     if suboptimal {
-                        plot.window_surface.recreate_swapchain = true;
+                        window_surface.recreate_swapchain = true;
                     }
 
-                    if let Some(image_fence) = &plot.window_surface.fences[image_i as usize] {
+                    if let Some(image_fence) = &window_surface.fences[image_i as usize] {
                         image_fence.wait(None).unwrap();
                     }
 
-                    let previous_fence_i = plot.window_surface.previous_fence_i;
-                    let previous_future = match plot.window_surface.fences[previous_fence_i as usize].clone() {
+                    let previous_fence_i = window_surface.previous_fence_i;
+                    let previous_future = match window_surface.fences[previous_fence_i as usize].clone() {
                         None => {
                             let mut now = vulkano::sync::now(self.device.clone());
                             now.cleanup_finished();
@@ -212,21 +665,23 @@ impl Plotter {
                         Some(fence) => fence.boxed(),
                     };
 
-                    let command_buffers = self.renderer.get_command_buffer(plot).unwrap();
+                    // the frame that last used these uniform buffers is done
+                    self.renderer.upload_cameras(window_surface.id(), image_i as usize, &self.plots);
+                    let command_buffer = self.renderer.get_command_buffer(window_surface.id()).unwrap()[image_i as usize].clone();
                     let future = previous_future
                         .join(acquire_future)
-                        .then_execute(self.queue.clone(), command_buffers[image_i as usize].clone())
+                        .then_execute(self.queue.clone(), command_buffer)
                         .unwrap()
                         .then_swapchain_present(
                             self.queue.clone(),
-                            SwapchainPresentInfo::swapchain_image_index(plot.window_surface.swapchain.clone(), image_i),
+                            SwapchainPresentInfo::swapchain_image_index(window_surface.swapchain.clone(), image_i),
                             )
                         .then_signal_fence_and_flush();
 
-                    plot.window_surface.fences[image_i as usize] = match future.map_err(Validated::unwrap) {
+                    window_surface.fences[image_i as usize] = match future.map_err(Validated::unwrap) {
                         Ok(value) => Some(Arc::new(value)),
                         Err(VulkanError::OutOfDate) => {
-                            plot.window_surface.recreate_swapchain = true;
+                            window_surface.recreate_swapchain = true;
                             None
                         }
                         Err(e) => {
@@ -235,13 +690,15 @@ impl Plotter {
                         }
                     };
 
-                    plot.window_surface.previous_fence_i = image_i;
+                    window_surface.previous_fence_i = image_i;
                 }
             }
             _ => (),
 
         });
 
+        self.event_loop = Some(event_loop);
+        self.running = false;
         self
     }
 }
@@ -293,9 +750,66 @@ impl Plotter {
 //         .collect()
 // }
 
+// plots of the figure, the cells of the grid of the host or the host alone
+fn figure_ids(plots: &HashMap<PlotId, Plot>, host: PlotId) -> Vec<PlotId> {
+    match plots[&host].subplots.is_empty() {
+        true => vec![host],
+        false => plots[&host].subplots.clone(),
+    }
+}
+
+fn figure_plots(plots: &HashMap<PlotId, Plot>, host: PlotId) -> Vec<&Plot> {
+    figure_ids(plots, host)
+        .iter()
+        .map(|id| &plots[id])
+        .collect()
+}
+
+// the figures shown in a window take its size
+fn sync_canvases(plots: &mut HashMap<PlotId, Plot>, windows: &HashMap<PlotId, WindowSurface>) {
+    for (host, window_surface) in windows {
+        for id in figure_ids(plots, *host) {
+            plots.get_mut(&id).unwrap().set_extent(window_surface.inner_size().into());
+        }
+    }
+}
+
+fn find_window(windows: &HashMap<PlotId, WindowSurface>, window_id: WindowId) -> Option<PlotId> {
+    windows.iter()
+        .find(|(_, window_surface)| window_surface.id() == window_id)
+        .map(|(id, _)| *id)
+}
+
+fn create_device(
+    physical_device: Arc<PhysicalDevice>,
+    queue_family_index: u32,
+    device_extensions: DeviceExtensions,
+) -> (Arc<Device>, Arc<Queue>, Renderer) {
+    let (device, mut queues) = Device::new(
+        physical_device,
+        DeviceCreateInfo {
+            queue_create_infos: vec![QueueCreateInfo {
+                queue_family_index,
+                ..Default::default()
+            }],
+            enabled_extensions: device_extensions,
+            ..Default::default()
+        }
+        )
+        .expect("failed to create device");
+
+    let queue = queues.next().unwrap();
+    let memory_allocator = Arc::new(StandardMemoryAllocator::new_default(device.clone()));
+    // let descriptor_set_allocator = StandardDescriptorSetAllocator::new(device.clone(), Default::default());
+    let renderer = Renderer::new(device.clone(), memory_allocator.clone(), queue.clone());
+
+    (device, queue, renderer)
+}
+
+// without a surface any device with a graphics queue will do
 fn select_physical_device(
     instance: &Arc<Instance>,
-    surface: &Arc<Surface>,
+    surface: Option<&Arc<Surface>>,
     device_extensions: &DeviceExtensions,
 ) -> (Arc<PhysicalDevice>, u32) {
     instance
@@ -308,7 +822,7 @@ fn select_physical_device(
                 .enumerate()
                 .position(|(i, q)| {
                     q.queue_flags.contains(QueueFlags::GRAPHICS)
-                        && p.surface_support(i as u32, surface).unwrap_or(false)
+                        && surface.is_none_or(|surface| p.surface_support(i as u32, surface).unwrap_or(false))
                 })
                 .map(|q| (p, q as u32))
         })
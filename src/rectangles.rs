@@ -0,0 +1,190 @@
+use vulkano::{buffer::BufferContents, pipeline::graphics::vertex_input::Vertex};
+
+use crate::legend::{Series, Swatch};
+
+#[derive(BufferContents, Vertex, Clone, Debug)]
+#[repr(C)]
+pub struct Rectangle {
+    // lower left corner
+    #[format(R32G32B32_SFLOAT)]
+    pub position: [f32; 3],
+    #[format(R32G32_SFLOAT)]
+    pub size: [f32; 2],
+    #[format(R32G32B32A32_SFLOAT)]
+    pub color: [f32; 4],
+}
+
+pub mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec3 local_position;
+
+            //per-instance data
+            layout(location = 1) in vec3 position;
+            layout(location = 2) in vec2 size;
+            layout(location = 3) in vec4 color;
+
+            // uniform data
+            layout(set = 0, binding = 0) uniform UBO
+            {
+                mat4 projection;
+                mat4 modelview;
+                // data coordinates to [-1; 1]
+                mat4 data;
+            } ubo;
+
+            //out
+            layout(location = 0) out vec4 o_color;
+
+            void main() {
+                o_color = color;
+
+                vec2 corner = (local_position.xy + 1.) * 0.5 * size;
+                gl_Position = ubo.projection * ubo.modelview * ubo.data * vec4(position + vec3(corner, 0.), 1.0);
+            }
+            ",
+    }
+}
+
+pub mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec4 color;
+
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                f_color = color;
+            }
+        ",
+    }
+}
+
+impl Rectangle {
+    // corners can be given in any order
+    pub fn from_corners(a: [f32; 2], b: [f32; 2], color: [f32; 4]) -> Self {
+        Self {
+            position: [a[0].min(b[0]), a[1].min(b[1]), 0.],
+            size: [(b[0] - a[0]).abs(), (b[1] - a[1]).abs()],
+            color,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BarLayout {
+    // series side by side inside the bar width
+    Grouped,
+    // each series on top of the previous ones
+    Stacked,
+}
+
+// bar chart description, see `Plotter::bars`
+#[derive(Clone, Debug)]
+pub struct Bars {
+    positions: Vec<f32>,
+    // values, colour and legend label
+    series: Vec<(Vec<f32>, [f32; 4], Option<String>)>,
+    width: f32,
+    baseline: f32,
+    layout: BarLayout,
+    horizontal: bool,
+}
+
+impl Bars {
+    pub fn new(positions: Vec<f32>, values: Vec<f32>, color: [f32; 4]) -> Self {
+        Self {
+            positions,
+            series: vec![(values, color, None)],
+            width: 0.8,
+            baseline: 0.,
+            layout: BarLayout::Grouped,
+            horizontal: false,
+        }
+    }
+
+    pub fn series(mut self, values: Vec<f32>, color: [f32; 4]) -> Self {
+        self.series.push((values, color, None));
+        self
+    }
+
+    // labels the last series added
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        if let Some(series) = self.series.last_mut() {
+            series.2 = Some(label.into());
+        }
+        self
+    }
+
+    pub(crate) fn legend_series(&self) -> Vec<Series> {
+        self.series.iter()
+            .map(|(_, color, label)| Series { label: label.clone(), swatch: Swatch::Patch { color: *color } })
+            .collect()
+    }
+
+    // total width of the bars at a position, shared by grouped series
+    pub fn width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn baseline(mut self, baseline: f32) -> Self {
+        self.baseline = baseline;
+        self
+    }
+
+    pub fn layout(mut self, layout: BarLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn stacked(self) -> Self {
+        self.layout(BarLayout::Stacked)
+    }
+
+    // bars grow along x, positions are on the y axis
+    pub fn horizontal(mut self) -> Self {
+        self.horizontal = true;
+        self
+    }
+
+    pub fn rectangles(&self) -> Vec<Rectangle> {
+        let n = self.series.len().max(1);
+        let width = match self.layout {
+            BarLayout::Grouped => self.width / n as f32,
+            BarLayout::Stacked => self.width,
+        };
+
+        let mut bottoms = vec![self.baseline; self.positions.len()];
+        let mut rectangles = vec![];
+        for (i, (values, color, _)) in self.series.iter().enumerate() {
+            let shift = match self.layout {
+                BarLayout::Grouped => (i as f32 - (n - 1) as f32 * 0.5) * width,
+                BarLayout::Stacked => 0.,
+            };
+
+            for ((position, value), bottom) in self.positions.iter().zip(values).zip(bottoms.iter_mut()) {
+                if !position.is_finite() || !value.is_finite() {
+                    continue;
+                }
+
+                let center = position + shift;
+                let (a, b) = ([center - width * 0.5, *bottom], [center + width * 0.5, *bottom + value]);
+                let (a, b) = if self.horizontal { ([a[1], a[0]], [b[1], b[0]]) } else { (a, b) };
+                rectangles.push(Rectangle::from_corners(a, b, *color));
+
+                if self.layout == BarLayout::Stacked {
+                    *bottom += value;
+                }
+            }
+        }
+
+        rectangles
+    }
+}
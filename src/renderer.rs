@@ -1,60 +1,197 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::{HashMap, HashSet}, error::Error, sync::Arc};
 
-use vulkano::{command_buffer::{allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents}, device::{Device, Queue}, memory::allocator::StandardMemoryAllocator, pipeline::graphics::viewport::Viewport};
+use image::RgbaImage;
+use vulkano::{command_buffer::{allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, BlitImageInfo, CommandBufferUsage, CopyImageToBufferInfo, PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents}, device::{Device, Queue}, image::sampler::Filter, memory::allocator::StandardMemoryAllocator, pipeline::graphics::viewport::{Scissor, Viewport}, render_pass::{Framebuffer, RenderPass}, sync::{self, GpuFuture}};
 use winit::window::WindowId;
 
-use crate::{circle_manadger::CircleManadger, plot::Plot};
+use crate::{camera::CameraSlots, circle_manadger::CircleManadger, heatmap_manager::HeatmapManager, instance_buffer::Changes, line_manager::LineManager, offscreen::{self, OffscreenTarget}, plot::{Layer, Overlay, OverlayKey, Plot, PlotId}, rectangle_manager::RectangleManager, text_manager::TextManager, window_surface::{supported_samples, WindowSurface}};
 
 pub struct Renderer {
-    // device: Arc<Device>,
+    device: Arc<Device>,
     queue: Arc<Queue>,
     command_buffer_allocator: StandardCommandBufferAllocator,
-    // memory_allocator: Arc<StandardMemoryAllocator>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
     circles_manadger: CircleManadger,
-    command_buffers: HashMap<WindowId, Vec<Arc<PrimaryAutoCommandBuffer>>>
+    line_manager: LineManager,
+    rectangle_manager: RectangleManager,
+    heatmap_manager: HeatmapManager,
+    text_manager: TextManager,
+    command_buffers: HashMap<WindowId, Vec<Arc<PrimaryAutoCommandBuffer>>>,
+    // camera part of the uniform buffers of each command buffer
+    cameras: HashMap<WindowId, Vec<CameraSlots>>,
+    // plots each window was last recorded with, kept once its command buffers are dropped as its frames
+    // may still be in flight
+    drawn: HashMap<WindowId, Vec<PlotId>>,
+    // what the overlay of each plot was last built from
+    overlay_keys: HashMap<PlotId, OverlayKey>,
+    // the pipelines of the managers were built for it
+    render_pass: Option<Arc<RenderPass>>,
 }
 
 impl Renderer {
     pub fn new(device: Arc<Device>, memory_allocator: Arc<StandardMemoryAllocator>, queue: Arc<Queue>) -> Self {
         let circles_manadger = CircleManadger::new(device.clone(), memory_allocator.clone());
+        let line_manager = LineManager::new(device.clone(), memory_allocator.clone());
+        let rectangle_manager = RectangleManager::new(device.clone(), memory_allocator.clone());
+        let heatmap_manager = HeatmapManager::new(device.clone(), memory_allocator.clone());
         let command_buffer_allocator = StandardCommandBufferAllocator::new(device.clone(), Default::default());
+        let text_manager = TextManager::new(device.clone(), memory_allocator.clone(), queue.clone(), &command_buffer_allocator);
 
         Self {
-            // device,
+            device,
             queue,
             command_buffer_allocator,
-            // memory_allocator,
+            memory_allocator,
             circles_manadger,
-            command_buffers: HashMap::new()
+            line_manager,
+            rectangle_manager,
+            heatmap_manager,
+            text_manager,
+            command_buffers: HashMap::new(),
+            cameras: HashMap::new(),
+            drawn: HashMap::new(),
+            overlay_keys: HashMap::new(),
+            render_pass: None,
         }
     }
 
-    pub fn create_buffer<'a, I>(&mut self, plots: I) -> &mut Self
+    // uploads what changed in the plots. only the frames of the windows drawing instances overwritten in place
+    // are waited for, and only the command buffers drawing moved instances or changed overlays are dropped
+    pub fn create_buffer<'a, 'b, I, W>(&mut self, plots: I, windows: W) -> &mut Self
     where
-        I: IntoIterator<Item = &'a Plot>,
+        I: IntoIterator<Item = &'a Plot> + Clone,
         I::IntoIter: ExactSizeIterator,
+        W: IntoIterator<Item = &'b WindowSurface>,
+    {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+            )
+            .unwrap();
+
+        let mut changes = Changes::default();
+        self.heatmap_manager.create_buffers(&mut builder, plots.clone(), &mut changes);
+        self.rectangle_manager.create_buffers(&mut builder, plots.clone(), &mut changes);
+        self.line_manager.create_buffers(&mut builder, plots.clone(), &mut changes);
+        self.circles_manadger.create_buffers(&mut builder, plots.clone(), &mut changes);
+        self.text_manager.create_buffers(&mut builder, plots.clone(), &mut changes);
+        let overlays = self.create_overlay_buffers(&mut builder, plots);
+        changes.moved.extend(overlays);
+
+        // frames still in flight may be reading the ranges about to be overwritten
+        for window_surface in windows {
+            if self.draws(window_surface.id(), &changes.overwritten) {
+                window_surface.wait_frames();
+            }
+        }
+        sync::now(self.device.clone())
+            .then_execute(self.queue.clone(), builder.build().unwrap())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        self.drop_command_buffers(&changes.moved);
+
+        self
+    }
+
+    // the overlay follows the limits and the size of the plot, rebuilt alone while the data stays in place
+    pub fn update_overlays<'a, I>(&mut self, plots: I) -> &mut Self
+    where
+        I: IntoIterator<Item = &'a Plot>,
     {
-        self.circles_manadger.create_buffers(plots);
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+            )
+            .unwrap();
+
+        let changed = self.create_overlay_buffers(&mut builder, plots);
+        if changed.is_empty() {
+            return self;
+        }
+        // a new colorbar is only uploaded to new textures
+        sync::now(self.device.clone())
+            .then_execute(self.queue.clone(), builder.build().unwrap())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        self.drop_command_buffers(&changed);
 
         self
     }
 
+    // the overlays whose key changed are built again, once for all the managers. returns their plots
+    fn create_overlay_buffers<'a, I>(&mut self, builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, plots: I) -> HashSet<PlotId>
+    where
+        I: IntoIterator<Item = &'a Plot>,
+    {
+        let plots: Vec<&Plot> = plots.into_iter().collect();
+        self.overlay_keys.retain(|id, _| plots.iter().any(|plot| plot.id() == *id));
+
+        let mut overlays: Vec<(PlotId, Overlay)> = vec![];
+        for plot in plots {
+            let key = plot.overlay_key();
+            if self.overlay_keys.insert(plot.id(), key) != Some(key) {
+                overlays.push((plot.id(), plot.overlay()));
+            }
+        }
+
+        self.heatmap_manager.create_overlay_buffers(builder, &overlays);
+        self.rectangle_manager.create_overlay_buffers(&overlays);
+        self.line_manager.create_overlay_buffers(&overlays);
+        self.circles_manadger.create_overlay_buffers(&overlays);
+        self.text_manager.create_overlay_buffers(&overlays);
+
+        overlays.into_iter().map(|(id, _)| id).collect()
+    }
+
+    // whether the window was last recorded drawing any of the plots
+    fn draws(&self, window_id: WindowId, plots: &HashSet<PlotId>) -> bool {
+        self.drawn.get(&window_id).is_some_and(|drawn| drawn.iter().any(|id| plots.contains(id)))
+    }
+
+    // the command buffers drawing the plots still draw their replaced buffers and ranges,
+    // the plotter records new ones for these windows
+    fn drop_command_buffers(&mut self, plots: &HashSet<PlotId>) {
+        let windows: Vec<WindowId> = self.drawn.keys().copied().filter(|id| self.draws(*id, plots)).collect();
+        for window_id in windows {
+            self.command_buffers.remove(&window_id);
+            self.cameras.remove(&window_id);
+        }
+    }
+
+    // the pipelines only depend on the render pass, recording again after a pan or a resize keeps them
+    fn build_pipelines(&mut self, render_pass: Arc<RenderPass>) {
+        if self.render_pass.as_ref().is_some_and(|built| Arc::ptr_eq(built, &render_pass)) {
+            return;
+        }
+        self.render_pass = Some(render_pass.clone());
+        self.heatmap_manager.build_pipeline(render_pass.clone());
+        self.rectangle_manager.build_pipeline(render_pass.clone());
+        self.line_manager.build_pipeline(render_pass.clone());
+        self.circles_manadger.build_pipeline(render_pass.clone());
+        self.text_manager.build_pipeline(render_pass);
+    }
+
+    // one command buffer per swapchain image of the window, plots are the ones drawn in it
     pub fn build_command_buffers(
         &mut self,
-        // command_buffer_allocator: &StandardCommandBufferAllocator,
-        // queue: &Arc<Queue>,
-        plot: &Plot,
-        // circles : &mut CircleManadger,
-        viewport : Viewport,
-        // descriptor_set_allocator : &StandardDescriptorSetAllocator,
+        window_surface: &WindowSurface,
+        plots: &[&Plot],
         )
     {
-        self.circles_manadger.build_pipeline(plot.window_surface.render_pass.clone(), viewport);
-        // circles.build_pipeline(render_pass.clone(), viewport);
+        self.build_pipelines(window_surface.render_pass.clone());
 
-        let command_buffers = plot.window_surface.framebuffers
+        let (command_buffers, cameras) = window_surface.framebuffers
             .iter()
-            .map(|framebuffer| {
+            .zip(&window_surface.downsampling)
+            .map(|(framebuffer, chain)| {
                 let mut builder = AutoCommandBufferBuilder::primary(
                     &self.command_buffer_allocator,
                     self.queue.queue_family_index(),
@@ -62,34 +199,144 @@ impl Renderer {
                     )
                     .unwrap();
 
-                builder
-                    .begin_render_pass(
-                        RenderPassBeginInfo {
-                            clear_values: vec![Some([0.0, 0.0, 1.0, 1.0].into())],
-                            ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
-                        },
-                        SubpassBeginInfo {
-                            contents: SubpassContents::Inline,
-                            ..Default::default()
-                        },
-                        )
-                    .unwrap();
+                let mut cameras = vec![];
+                self.record(&mut builder, framebuffer.clone(), plots, window_surface.supersampling(), &mut cameras);
+                // halves the size of the supersampled frame down to the swapchain image
+                for images in chain.windows(2) {
+                    builder
+                        .blit_image(BlitImageInfo {
+                            filter: Filter::Linear,
+                            ..BlitImageInfo::images(images[0].clone(), images[1].clone())
+                        })
+                        .unwrap();
+                }
+                (builder.build().unwrap(), cameras)
+            })
+        .unzip();
 
-                //draw here
-                self.circles_manadger.draw(&mut builder, plot);
+        self.command_buffers
+            .insert(window_surface.id(), command_buffers);
+        self.cameras
+            .insert(window_surface.id(), cameras);
+        self.drawn
+            .insert(window_surface.id(), plots.iter().map(|plot| plot.id()).collect());
+    }
 
-                builder.end_render_pass(Default::default())
-                    .unwrap();
+    pub fn get_command_buffer(&mut self, window_id: WindowId) -> Option<&mut Vec<Arc<PrimaryAutoCommandBuffer>>> {
+        self.command_buffers.get_mut(&window_id)
+    }
 
-                builder.build().unwrap()
-            })
-        .collect();
+    pub fn remove_command_buffers(&mut self, window_id: WindowId) {
+        self.command_buffers.remove(&window_id);
+        self.cameras.remove(&window_id);
+    }
 
-        self.command_buffers
-            .insert(plot.id(), command_buffers);
+    // writes the cameras of the plots into the uniform buffers of the command buffer of the image,
+    // which the gpu must be done with
+    pub fn upload_cameras(&mut self, window_id: WindowId, image: usize, plots: &HashMap<PlotId, Plot>) {
+        let cameras = match self.cameras.get(&window_id).and_then(|cameras| cameras.get(image)) {
+            Some(cameras) => cameras,
+            None => return,
+        };
+        for (id, camera) in cameras {
+            if let Some(plot) = plots.get(id) {
+                *camera.write().unwrap() = plot.camera.get_data();
+            }
+        }
+    }
+
+    // renders the plots into a new image and waits for the gpu to read it back,
+    // the first one gives the anti-aliasing settings
+    pub fn render_offscreen(&mut self, plots: &[&Plot], width: u32, height: u32) -> Result<RgbaImage, Box<dyn Error>> {
+        let limit = offscreen::max_extent(&self.device);
+        if width == 0 || height == 0 || width.max(height) > limit {
+            return Err(format!("can not render an image of {width}x{height} pixels, the device supports 1 to {limit}").into());
+        }
+        // supersampling is lowered until the rendered image fits
+        let mut factor = plots[0].supersampling();
+        while factor > 1 && width.max(height) * factor > limit {
+            factor /= 2;
+        }
+
+        let samples = supported_samples(&self.device, plots[0].samples());
+        let target = OffscreenTarget::new(self.device.clone(), self.memory_allocator.clone(), width * factor, height * factor, samples)?;
+        self.build_pipelines(target.render_pass.clone());
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+            )
+            .unwrap();
+
+        self.record(&mut builder, target.framebuffer.clone(), plots, factor, &mut vec![]);
+        builder
+            .copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(target.image.clone(), target.readback.clone()))
+            .unwrap();
+
+        sync::now(self.device.clone())
+            .then_execute(self.queue.clone(), builder.build().unwrap())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        Ok(offscreen::downsample(&target.to_image(), factor))
     }
 
-    pub fn get_command_buffer(&mut self, plot: &Plot) -> Option<&mut Vec<Arc<PrimaryAutoCommandBuffer>>> {
-        self.command_buffers.get_mut(&plot.id())
+    fn record(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        framebuffer: Arc<Framebuffer>,
+        plots: &[&Plot],
+        // rendered size over the size of the canvas
+        scale: u32,
+        cameras: &mut CameraSlots,
+        )
+    {
+        builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    // only the first attachment is cleared, the resolved one is overwritten
+                    clear_values: std::iter::once(Some([0.0, 0.0, 1.0, 1.0].into()))
+                        .chain(std::iter::repeat(None))
+                        .take(framebuffer.attachments().len())
+                        .collect(),
+                    ..RenderPassBeginInfo::framebuffer(framebuffer)
+                },
+                SubpassBeginInfo {
+                    contents: SubpassContents::Inline,
+                    ..Default::default()
+                },
+                )
+            .unwrap();
+
+        for plot in plots {
+            // each plot is clipped to its cell of the grid
+            let offset = plot.offset().map(|v| v * scale);
+            let extent = plot.extent().map(|v| v * scale);
+            builder
+                .set_viewport(0, [Viewport {
+                    offset: offset.map(|v| v as f32),
+                    extent: extent.map(|v| v as f32),
+                    depth_range: 0.0..=1.0,
+                }].into_iter().collect())
+                .unwrap()
+                .set_scissor(0, [Scissor { offset, extent }].into_iter().collect())
+                .unwrap();
+
+            //draw here, texts over markers over lines over rectangles over heatmaps, the overlay over the data
+            for layer in [Layer::Data, Layer::Overlay] {
+                self.heatmap_manager.draw(builder, plot, layer, cameras);
+                self.rectangle_manager.draw(builder, plot, layer, cameras);
+                self.line_manager.draw(builder, plot, layer, cameras);
+                self.circles_manadger.draw(builder, plot, layer, cameras);
+                self.text_manager.draw(builder, plot, layer, cameras);
+            }
+        }
+
+        builder.end_render_pass(Default::default())
+            .unwrap();
     }
 }
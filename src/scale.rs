@@ -0,0 +1,221 @@
+use crate::ticks::{self, Ticks};
+
+// how data values are spread along an axis
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Scale {
+    #[default]
+    Linear,
+    Log10,
+    Ln,
+    // linear within [-threshold; threshold], logarithmic outside
+    SymLog { threshold: f32 },
+    // data to axis coordinates and back, ticks are placed linearly in data space
+    Custom { forward: fn(f32) -> f32, inverse: fn(f32) -> f32 },
+}
+
+const SUPERSCRIPTS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+
+// "10³", "e⁻²"
+fn power(base: &str, exponent: i64) -> String {
+    let sign = if exponent < 0 { "⁻" } else { "" };
+    let digits: String = exponent.unsigned_abs()
+        .to_string()
+        .chars()
+        .map(|c| SUPERSCRIPTS[c.to_digit(10).unwrap() as usize])
+        .collect();
+
+    format!("{base}{sign}{digits}")
+}
+
+// integers of the range, every few of them when there are more than `max_ticks`
+fn integers(min: f64, max: f64, max_ticks: usize) -> (Vec<f64>, i64) {
+    let (first, last) = ((min - 1e-9).ceil() as i64, (max + 1e-9).floor() as i64);
+    let every = ((last - first + 1) as f64 / max_ticks.max(1) as f64).ceil().max(1.) as i64;
+
+    let major = (first..=last)
+        .filter(|i| i.rem_euclid(every) == 0)
+        .map(|i| i as f64)
+        .collect();
+    (major, every)
+}
+
+impl Scale {
+    // data to axis coordinates, None for the values the scale can not show
+    pub fn forward(&self, value: f32) -> Option<f32> {
+        let axis = match self {
+            Scale::Linear => value,
+            Scale::Log10 if value > 0. => value.log10(),
+            Scale::Ln if value > 0. => value.ln(),
+            Scale::Log10 | Scale::Ln => return None,
+            Scale::SymLog { threshold } => {
+                let threshold = threshold.abs().max(f32::MIN_POSITIVE);
+                match value.abs() <= threshold {
+                    true => value / threshold,
+                    false => value.signum() * (1. + (value.abs() / threshold).log10()),
+                }
+            }
+            Scale::Custom { forward, .. } => forward(value),
+        };
+
+        axis.is_finite().then_some(axis)
+    }
+
+    pub fn inverse(&self, axis: f32) -> f32 {
+        match self {
+            Scale::Linear => axis,
+            Scale::Log10 => 10f32.powf(axis),
+            Scale::Ln => axis.exp(),
+            Scale::SymLog { threshold } => {
+                let threshold = threshold.abs().max(f32::MIN_POSITIVE);
+                match axis.abs() <= 1. {
+                    true => axis * threshold,
+                    false => axis.signum() * threshold * 10f32.powf(axis.abs() - 1.),
+                }
+            }
+            Scale::Custom { inverse, .. } => inverse(axis),
+        }
+    }
+
+    // ticks in axis coordinates over limits in axis coordinates, with the labels of the major ones
+    pub fn locate(&self, [min, max]: [f32; 2], max_ticks: usize) -> (Ticks, Vec<String>) {
+        let (min, max) = (min.min(max) as f64, min.max(max) as f64);
+
+        match self {
+            Scale::Linear => {
+                let ticks = ticks::locate(min, max, max_ticks);
+                let labels = ticks.labels();
+                (ticks, labels)
+            }
+            // less than a decade falls back to linear ticks
+            Scale::Log10 | Scale::Ln if max - min < 1. => self.data_ticks([min, max], max_ticks),
+            Scale::Log10 => {
+                let (major, every) = integers(min, max, max_ticks);
+                // 2 to 9 times each decade, the skipped decades when there are too many
+                let minor = match every {
+                    1 => (min.floor() as i64..=max.floor() as i64)
+                        .flat_map(|decade| (2..10).map(move |m| decade as f64 + (m as f64).log10()))
+                        .filter(|v| (min..=max).contains(v))
+                        .collect(),
+                    _ => integers(min, max, usize::MAX).0.into_iter().filter(|v| !major.contains(v)).collect(),
+                };
+                let labels = major.iter().map(|v| power("10", *v as i64)).collect();
+                (Ticks { major, minor, step: every as f64 }, labels)
+            }
+            Scale::Ln => {
+                let (major, every) = integers(min, max, max_ticks);
+                let labels = major.iter().map(|v| power("e", *v as i64)).collect();
+                (Ticks { major, minor: vec![], step: every as f64 }, labels)
+            }
+            // the linear part alone, or too small a range, gets linear ticks
+            Scale::SymLog { .. } if max - min < 2. => self.data_ticks([min, max], max_ticks),
+            Scale::SymLog { threshold } => {
+                let (major, every) = integers(min, max, max_ticks);
+                // 2 to 9 times each decade outside of the linear part
+                let minor = match every {
+                    // the decades below zero lie under their number, the one under -1 included
+                    1 => (min.floor() as i64..=max.ceil() as i64)
+                        .filter(|n| *n != 0)
+                        .flat_map(|n| (2..10).map(move |m| n as f64 + n.signum() as f64 * (m as f64).log10()))
+                        .filter(|v| (min..=max).contains(v))
+                        .collect(),
+                    _ => vec![],
+                };
+                let labels = major.iter()
+                    .map(|v| ticks::format(self.inverse(*v as f32) as f64, threshold.abs() as f64))
+                    .collect();
+                (Ticks { major, minor, step: every as f64 }, labels)
+            }
+            Scale::Custom { .. } => self.data_ticks([min, max], max_ticks),
+        }
+    }
+
+    // linear ticks in data space carried over to axis coordinates
+    fn data_ticks(&self, [min, max]: [f64; 2], max_ticks: usize) -> (Ticks, Vec<String>) {
+        let ticks = ticks::locate(self.inverse(min as f32) as f64, self.inverse(max as f32) as f64, max_ticks);
+        let forward = |v: &f64| self.forward(*v as f32).map(|v| v as f64);
+
+        let (major, labels) = ticks.major.iter()
+            .zip(ticks.labels())
+            .filter_map(|(v, label)| Some((forward(v)?, label)))
+            .unzip();
+        let minor = ticks.minor.iter().filter_map(forward).collect();
+
+        (Ticks { major, minor, step: ticks.step }, labels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_round_trip(scale: Scale, values: &[f32]) {
+        for value in values {
+            let back = scale.inverse(scale.forward(*value).unwrap());
+            assert!((back - value).abs() <= value.abs() * 1e-5, "{scale:?} {value} {back}");
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        assert_round_trip(Scale::Log10, &[1e-3, 0.5, 1., 42., 1e6]);
+        assert_round_trip(Scale::Ln, &[1e-3, 1., std::f32::consts::E, 1e6]);
+        assert_round_trip(Scale::SymLog { threshold: 1. }, &[-1e4, -3., -1., -0.5, 0., 0.25, 1., 7., 1e4]);
+        assert_round_trip(Scale::SymLog { threshold: 0.01 }, &[-5., -0.005, 0., 0.01, 20.]);
+    }
+
+    #[test]
+    fn symlog_is_continuous() {
+        let scale = Scale::SymLog { threshold: 2. };
+        assert_eq!(scale.forward(2.), Some(1.));
+        assert_eq!(scale.forward(-2.), Some(-1.));
+        assert_eq!(scale.forward(20.), Some(2.));
+        assert!((scale.forward(2.0001).unwrap() - 1.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn masks_non_positive() {
+        for scale in [Scale::Log10, Scale::Ln] {
+            for value in [0., -1., f32::NEG_INFINITY, f32::NAN] {
+                assert_eq!(scale.forward(value), None, "{scale:?} {value}");
+            }
+        }
+        assert_eq!(Scale::Linear.forward(f32::NAN), None);
+        assert_eq!(Scale::SymLog { threshold: 1. }.forward(-5.), Some(-(1. + 5f32.log10())));
+    }
+
+    #[test]
+    fn decade_ticks() {
+        let (ticks, labels) = Scale::Log10.locate([-1., 3.], 10);
+        assert_eq!(ticks.major, vec![-1., 0., 1., 2., 3.]);
+        assert_eq!(labels, vec!["10⁻¹", "10⁰", "10¹", "10²", "10³"]);
+        // 2 to 9 times each of the four decades
+        assert_eq!(ticks.minor.len(), 4 * 8);
+        assert!((ticks.minor[0] - (-1. + 2f64.log10())).abs() < 1e-12);
+
+        // every other decade, the skipped ones as minor ticks
+        let (ticks, _) = Scale::Log10.locate([0., 9.], 5);
+        assert_eq!(ticks.major, vec![0., 2., 4., 6., 8.]);
+        assert_eq!(ticks.minor, vec![1., 3., 5., 7., 9.]);
+
+        let (_, labels) = Scale::Ln.locate([0., 2.], 10);
+        assert_eq!(labels, vec!["e⁰", "e¹", "e²"]);
+
+        // mirrored on both sides of the linear part
+        let (ticks, _) = Scale::SymLog { threshold: 1. }.locate([-3., 3.], 10);
+        assert_eq!(ticks.major, vec![-3., -2., -1., 0., 1., 2., 3.]);
+        assert_eq!(ticks.minor.len(), 4 * 8);
+        for tick in &ticks.minor {
+            assert!(ticks.minor.iter().any(|v| (v + tick).abs() < 1e-12), "{tick} has no mirror");
+        }
+        assert!(ticks.minor.iter().any(|v| (-2. ..-1.).contains(v)));
+    }
+
+    #[test]
+    fn narrow_log_range_uses_linear_ticks() {
+        let (lower, upper) = (2f32.log10(), 5f32.log10());
+        let (ticks, labels) = Scale::Log10.locate([lower, upper], 5);
+
+        assert_eq!(labels, vec!["2", "3", "4", "5"]);
+        assert!(ticks.major.iter().all(|v| (lower as f64 - 1e-6..=upper as f64 + 1e-6).contains(v)));
+    }
+}
@@ -0,0 +1,372 @@
+use std::{fs, io, path::Path};
+
+use num::complex::Complex;
+
+const MISSING: [&str; 6] = ["", "na", "nan", "n/a", "null", "-"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delimiter {
+    // guessed from the file extension, then from the first line
+    Auto,
+    Char(char),
+    Whitespace,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Header {
+    // the first line is a header if one of its fields is not a number
+    Auto,
+    Present,
+    Absent,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ColumnRef {
+    Name(String),
+    Index(usize),
+}
+
+impl From<&str> for ColumnRef {
+    fn from(name: &str) -> Self {
+        ColumnRef::Name(name.to_string())
+    }
+}
+
+impl From<String> for ColumnRef {
+    fn from(name: String) -> Self {
+        ColumnRef::Name(name)
+    }
+}
+
+impl From<usize> for ColumnRef {
+    fn from(index: usize) -> Self {
+        ColumnRef::Index(index)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TableOptions {
+    pub delimiter: Delimiter,
+    pub header: Header,
+    pub comment: Option<char>,
+    // only keep these columns, in this order
+    pub columns: Option<Vec<ColumnRef>>,
+}
+
+impl Default for TableOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: Delimiter::Auto,
+            header: Header::Auto,
+            comment: Some('#'),
+            columns: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Column {
+    pub name: String,
+    pub values: Vec<f64>,
+}
+
+impl Column {
+    pub fn len(&self) -> usize { self.values.len() }
+    pub fn is_empty(&self) -> bool { self.values.is_empty() }
+
+    // Plotter::scatter input
+    pub fn to_f32(&self) -> Vec<f32> {
+        self.values.iter().map(|v| *v as f32).collect()
+    }
+
+    // my_fft input
+    pub fn to_complex(&self) -> Vec<Complex<f64>> {
+        self.values.iter().map(|v| Complex::new(*v, 0.)).collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Table {
+    pub columns: Vec<Column>,
+}
+
+impl Table {
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read_with(path, &TableOptions::default())
+    }
+
+    pub fn read_with<P: AsRef<Path>>(path: P, options: &TableOptions) -> io::Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+
+        let delimiter = match (options.delimiter, path.extension().and_then(|e| e.to_str())) {
+            (Delimiter::Auto, Some("csv")) => Delimiter::Char(','),
+            (Delimiter::Auto, Some("tsv")) => Delimiter::Char('\t'),
+            (delimiter, _) => delimiter,
+        };
+
+        Self::parse(&text, &TableOptions { delimiter, ..options.clone() })
+    }
+
+    // comments run from the comment character to the end of the line, outside of quoted fields
+    pub fn parse(text: &str, options: &TableOptions) -> io::Result<Self> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, strip_comment(line.trim_end_matches('\r'), options.comment)))
+            .filter(|(_, line)| !line.trim().is_empty())
+            .peekable();
+
+        let delimiter = match (options.delimiter, lines.peek()) {
+            (Delimiter::Auto, Some((_, line))) => guess_delimiter(line),
+            (Delimiter::Auto, None) => Delimiter::Whitespace,
+            (delimiter, _) => delimiter,
+        };
+
+        let names = match lines.peek() {
+            Some((_, line)) => {
+                let fields = split(line, delimiter);
+                let is_header = match options.header {
+                    Header::Present => true,
+                    Header::Absent => false,
+                    Header::Auto => fields.iter().any(|f| parse_value(f).is_none()),
+                };
+
+                if is_header {
+                    lines.next();
+                    Some(fields.into_iter().map(|f| f.to_string()).collect::<Vec<_>>())
+                } else {
+                    None
+                }
+            }
+            None => None,
+        };
+
+        let mut columns: Vec<Vec<f64>> = vec![vec![]; names.as_ref().map_or(0, |n| n.len())];
+        for (n_rows, (line_number, line)) in lines.enumerate() {
+            let fields = split(line, delimiter);
+
+            // rows wider than the previous ones add columns filled with NaN
+            while columns.len() < fields.len() {
+                columns.push(vec![f64::NAN; n_rows]);
+            }
+
+            for (i, column) in columns.iter_mut().enumerate() {
+                let value = match fields.get(i) {
+                    Some(field) => parse_value(field).ok_or_else(|| invalid_data(format!("line {line_number}, column {}: {field:?} is not a number", i + 1)))?,
+                    None => f64::NAN,
+                };
+                column.push(value);
+            }
+        }
+
+        let columns: Vec<Column> = columns
+            .into_iter()
+            .enumerate()
+            .map(|(i, values)| Column {
+                name: names.as_ref().and_then(|n| n.get(i)).cloned().unwrap_or_else(|| i.to_string()),
+                values,
+            })
+            .collect();
+
+        let table = Self { columns };
+        match &options.columns {
+            Some(selection) => table.select(selection),
+            None => Ok(table),
+        }
+    }
+
+    pub fn n_columns(&self) -> usize { self.columns.len() }
+    pub fn n_rows(&self) -> usize { self.columns.first().map_or(0, |c| c.len()) }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.columns.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    pub fn column<C: Into<ColumnRef>>(&self, column: C) -> Option<&Column> {
+        match column.into() {
+            ColumnRef::Name(name) => self.columns.iter().find(|c| c.name == name),
+            ColumnRef::Index(index) => self.columns.get(index),
+        }
+    }
+
+    pub fn select(&self, columns: &[ColumnRef]) -> io::Result<Self> {
+        let columns = columns
+            .iter()
+            .map(|c| self.column(c.clone()).cloned().ok_or_else(|| invalid_data(format!("no column {c:?}"))))
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self { columns })
+    }
+
+    pub fn f32<C: Into<ColumnRef>>(&self, column: C) -> Option<Vec<f32>> {
+        self.column(column).map(Column::to_f32)
+    }
+
+    pub fn f64<C: Into<ColumnRef>>(&self, column: C) -> Option<Vec<f64>> {
+        self.column(column).map(|c| c.values.clone())
+    }
+
+    pub fn complex<C: Into<ColumnRef>>(&self, column: C) -> Option<Vec<Complex<f64>>> {
+        self.column(column).map(Column::to_complex)
+    }
+}
+
+// characters of the line outside of double quotes, with their byte offset
+fn unquoted(line: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    let mut quoted = false;
+    line.char_indices().filter(move |(_, c)| {
+        if *c == '"' {
+            quoted = !quoted;
+        }
+        !quoted && *c != '"'
+    })
+}
+
+fn strip_comment(line: &str, comment: Option<char>) -> &str {
+    let Some(comment) = comment else { return line };
+    match unquoted(line).find(|(_, c)| *c == comment) {
+        Some((i, _)) => &line[..i],
+        None => line,
+    }
+}
+
+fn guess_delimiter(line: &str) -> Delimiter {
+    [',', '\t', ';']
+        .into_iter()
+        .find(|d| unquoted(line).any(|(_, c)| c == *d))
+        .map_or(Delimiter::Whitespace, Delimiter::Char)
+}
+
+// a delimiter between double quotes is part of the field
+fn split(line: &str, delimiter: Delimiter) -> Vec<&str> {
+    match delimiter {
+        Delimiter::Whitespace | Delimiter::Auto => line.split_whitespace().collect(),
+        Delimiter::Char(d) => {
+            let ends = unquoted(line)
+                .filter(|(_, c)| *c == d)
+                .map(|(i, _)| i)
+                .chain(std::iter::once(line.len()));
+            let mut start = 0;
+            ends.map(|end| {
+                    let f = line[start..end].trim();
+                    start = end + d.len_utf8();
+                    f.strip_prefix('"').and_then(|f| f.strip_suffix('"')).unwrap_or(f)
+                })
+                .collect()
+        }
+    }
+}
+
+fn parse_value(field: &str) -> Option<f64> {
+    if MISSING.contains(&field.to_ascii_lowercase().as_str()) {
+        return Some(f64::NAN);
+    }
+    field.parse().ok()
+}
+
+fn invalid_data(error: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Table {
+        Table::parse(text, &TableOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn header_detection() {
+        let table = parse("time,signal\n0,1\n1,2\n");
+        assert_eq!(table.names(), vec!["time", "signal"]);
+        assert_eq!(table.f64("signal").unwrap(), vec![1., 2.]);
+
+        let table = parse("0,1\n1,2\n");
+        assert_eq!(table.names(), vec!["0", "1"]);
+        assert_eq!(table.n_rows(), 2);
+
+        let options = TableOptions { header: Header::Present, ..Default::default() };
+        let table = Table::parse("1,2\n3,4\n", &options).unwrap();
+        assert_eq!(table.names(), vec!["1", "2"]);
+        assert_eq!(table.f64(0).unwrap(), vec![3.]);
+    }
+
+    #[test]
+    fn delimiter_guessing() {
+        for text in ["1,2\n3,4", "1\t2\n3\t4", "1;2\n3;4", "1 2\n3   4"] {
+            let table = parse(text);
+            assert_eq!(table.n_columns(), 2, "{text:?}");
+            assert_eq!(table.f64(1).unwrap(), vec![2., 4.], "{text:?}");
+        }
+    }
+
+    #[test]
+    fn missing_values() {
+        let table = parse("a,b,c\n1,,3\nNA,5,null\n7,8\n");
+        let b = table.f64("b").unwrap();
+        assert!(b[0].is_nan());
+        assert_eq!(b[1..], [5., 8.]);
+
+        let c = table.f64("c").unwrap();
+        assert_eq!(c[0], 3.);
+        assert!(c[1].is_nan() && c[2].is_nan());
+        assert!(table.f64("a").unwrap()[1].is_nan());
+    }
+
+    #[test]
+    fn wider_rows_add_columns() {
+        let table = parse("1 2\n3 4 5\n");
+        assert_eq!(table.n_columns(), 3);
+        assert!(table.f64(2).unwrap()[0].is_nan());
+        assert_eq!(table.f64(2).unwrap()[1], 5.);
+    }
+
+    #[test]
+    fn column_selection() {
+        let options = TableOptions {
+            columns: Some(vec!["c".into(), 0.into()]),
+            ..Default::default()
+        };
+        let table = Table::parse("a,b,c\n1,2,3\n", &options).unwrap();
+        assert_eq!(table.names(), vec!["c", "a"]);
+        assert_eq!(table.f64(0).unwrap(), vec![3.]);
+
+        let options = TableOptions { columns: Some(vec!["d".into()]), ..Default::default() };
+        assert!(Table::parse("a,b,c\n1,2,3\n", &options).is_err());
+    }
+
+    #[test]
+    fn comments() {
+        let table = parse("# recorded on the bench\nx,y # volts\n  # indented comment\n1,2 # first\n3,4\n");
+        assert_eq!(table.names(), vec!["x", "y"]);
+        assert_eq!(table.f64("y").unwrap(), vec![2., 4.]);
+
+        // kept inside quotes
+        let options = TableOptions { header: Header::Present, ..Default::default() };
+        let table = Table::parse("\"#id\",value\n1,2\n", &options).unwrap();
+        assert_eq!(table.names(), vec!["#id", "value"]);
+
+        let options = TableOptions { comment: None, header: Header::Absent, ..Default::default() };
+        assert!(Table::parse("1,2 # first\n", &options).is_err());
+    }
+
+    #[test]
+    fn quoted_delimiter() {
+        let table = parse("\"time, s\",value\n0,1\n1,2\n");
+        assert_eq!(table.names(), vec!["time, s", "value"]);
+        assert_eq!(table.f64("value").unwrap(), vec![1., 2.]);
+
+        // nor does it count when guessing the delimiter
+        let table = parse("\"time, s\"\tvalue\n0\t1\n");
+        assert_eq!(table.names(), vec!["time, s", "value"]);
+    }
+
+    #[test]
+    fn invalid_field() {
+        let error = Table::parse("1,2\n3,x\n", &TableOptions { header: Header::Absent, ..Default::default() }).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("line 2, column 2"));
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+
+use ab_glyph::{point, Font, FontRef, GlyphId, PxScale, ScaleFont};
+use vulkano::{buffer::BufferContents, pipeline::graphics::vertex_input::Vertex};
+
+static FONT: &[u8] = include_bytes!("../assets/DejaVuSans.ttf");
+
+// size the glyphs are rasterized at, bigger texts are scaled up from it
+const ATLAS_PX: f32 = 48.;
+const ATLAS_WIDTH: u32 = 1024;
+// empty pixels around each glyph so the linear filtering does not bleed
+const PADDING: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+}
+
+// a string anchored at a position, laid out in pixels around it
+#[derive(Clone, Debug)]
+pub struct Text {
+    pub position: [f32; 2],
+    pub string: String,
+    // font size in pixels
+    pub size: f32,
+    pub color: [f32; 4],
+    // where the anchor is along and across the text
+    pub align: [Align; 2],
+    // counter-clockwise around the anchor, in radians
+    pub rotation: f32,
+}
+
+impl Text {
+    pub fn new(position: [f32; 2], string: impl Into<String>) -> Self {
+        Self {
+            position,
+            string: string.into(),
+            size: 14.,
+            color: [1., 1., 1., 1.],
+            align: [Align::Start, Align::Start],
+            rotation: 0.,
+        }
+    }
+
+    pub fn size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn align(mut self, horizontal: Align, vertical: Align) -> Self {
+        self.align = [horizontal, vertical];
+        self
+    }
+
+    pub fn rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+}
+
+// one textured quad per character
+#[derive(BufferContents, Vertex, Clone, Debug)]
+#[repr(C)]
+pub struct Glyph {
+    #[format(R32G32B32_SFLOAT)]
+    pub anchor: [f32; 3],
+    // lower left corner from the anchor in pixels, y up, before the rotation
+    #[format(R32G32_SFLOAT)]
+    pub offset: [f32; 2],
+    #[format(R32G32_SFLOAT)]
+    pub size: [f32; 2],
+    // top left and bottom right corners in the atlas
+    #[format(R32G32B32A32_SFLOAT)]
+    pub uv: [f32; 4],
+    #[format(R32G32B32A32_SFLOAT)]
+    pub color: [f32; 4],
+    #[format(R32_SFLOAT)]
+    pub rotation: f32,
+}
+
+pub mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec3 local_position;
+
+            //per-instance data
+            layout(location = 1) in vec3 anchor;
+            layout(location = 2) in vec2 offset;
+            layout(location = 3) in vec2 size;
+            layout(location = 4) in vec4 uv;
+            layout(location = 5) in vec4 color;
+            layout(location = 6) in float rotation;
+
+            // uniform data
+            layout(set = 0, binding = 0) uniform UBO
+            {
+                mat4 projection;
+                mat4 modelview;
+                // data coordinates to [-1; 1]
+                mat4 data;
+                vec2 viewport;
+            } ubo;
+
+            //out
+            layout(location = 0) out vec2 o_uv;
+            layout(location = 1) out vec4 o_color;
+
+            void main() {
+                vec2 t = (local_position.xy + 1.) * 0.5;
+                o_uv = mix(uv.xy, uv.zw, vec2(t.x, 1. - t.y));
+                o_color = color;
+
+                vec2 pixel = offset + t * size;
+                float c = cos(rotation);
+                float s = sin(rotation);
+                pixel = vec2(c * pixel.x - s * pixel.y, s * pixel.x + c * pixel.y);
+
+                // the pixels are y up, vulkan's clip space is y down
+                vec4 position = ubo.projection * ubo.modelview * ubo.data * vec4(anchor, 1.0);
+                position.xy += vec2(pixel.x, -pixel.y) * 2. / ubo.viewport * position.w;
+                gl_Position = position;
+            }
+            ",
+    }
+}
+
+pub mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec2 uv;
+            layout(location = 1) in vec4 color;
+
+            layout(set = 0, binding = 1) uniform sampler2D atlas;
+
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                float coverage = texture(atlas, uv).r;
+                if (coverage <= 0.)
+                    discard;
+
+                f_color = vec4(color.rgb, color.a * coverage);
+            }
+        ",
+    }
+}
+
+// placement of a rasterized character, in pixels of the atlas size
+#[derive(Clone, Copy, Debug)]
+struct GlyphInfo {
+    id: GlyphId,
+    uv: [f32; 4],
+    size: [f32; 2],
+    // lower left corner from the pen position, y up
+    bearing: [f32; 2],
+    advance: f32,
+}
+
+// coverage of the printable characters in a single channel texture
+pub(crate) struct GlyphAtlas {
+    font: FontRef<'static>,
+    glyphs: HashMap<char, GlyphInfo>,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl GlyphAtlas {
+    pub fn new() -> Self {
+        let font = FontRef::try_from_slice(FONT).expect("invalid bundled font");
+        let scale = PxScale::from(ATLAS_PX);
+        let scaled = font.as_scaled(scale);
+
+        let chars = (' '..='~').chain(['°', 'µ', '−', '±', '×', '·', '⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹', '⁻']);
+        let mut outlines = vec![];
+        let (mut x, mut y, mut row_height) = (0, 0, 0);
+        for c in chars {
+            let id = font.glyph_id(c);
+            let advance = scaled.h_advance(id);
+            let outline = font.outline_glyph(id.with_scale_and_position(scale, point(0., 0.)));
+
+            let (width, height, bounds) = match &outline {
+                Some(outline) => {
+                    let bounds = outline.px_bounds();
+                    (bounds.width() as u32, bounds.height() as u32, Some(bounds))
+                }
+                None => (0, 0, None),
+            };
+
+            // simple shelf packing
+            if x + width + 2 * PADDING > ATLAS_WIDTH {
+                x = 0;
+                y += row_height;
+                row_height = 0;
+            }
+
+            let position = [x + PADDING, y + PADDING];
+            x += width + 2 * PADDING;
+            row_height = row_height.max(height + 2 * PADDING);
+
+            let bearing = bounds.map_or([0., 0.], |b| [b.min.x, -b.max.y]);
+            outlines.push((c, id, position, [width, height], bearing, advance, outline));
+        }
+
+        let height = y + row_height;
+        let mut pixels = vec![0; (ATLAS_WIDTH * height) as usize];
+        let mut glyphs = HashMap::new();
+        for (c, id, [x, y], [width, h], bearing, advance, outline) in outlines {
+            if let Some(outline) = outline {
+                outline.draw(|dx, dy, coverage| {
+                    let i = (y + dy) * ATLAS_WIDTH + x + dx;
+                    pixels[i as usize] = (coverage.clamp(0., 1.) * 255.).round() as u8;
+                });
+            }
+
+            let uv = [
+                x as f32 / ATLAS_WIDTH as f32,
+                y as f32 / height as f32,
+                (x + width) as f32 / ATLAS_WIDTH as f32,
+                (y + h) as f32 / height as f32,
+            ];
+
+            glyphs.insert(c, GlyphInfo { id, uv, size: [width as f32, h as f32], bearing, advance });
+        }
+
+        Self {
+            font,
+            glyphs,
+            width: ATLAS_WIDTH,
+            height,
+            pixels,
+        }
+    }
+
+    // quads of the text, the anchor goes through the transform of its layer
+    pub fn layout(&self, text: &Text) -> Vec<Glyph> {
+        let anchor = [text.position[0], text.position[1], 0.];
+        let scaled = self.font.as_scaled(PxScale::from(ATLAS_PX));
+        let scale = text.size / ATLAS_PX;
+        let fallback = self.glyphs[&'?'];
+
+        let mut pen = 0.;
+        let mut previous: Option<GlyphId> = None;
+        let mut glyphs = vec![];
+        for c in text.string.chars() {
+            let info = self.glyphs.get(&c).copied().unwrap_or(fallback);
+            if let Some(previous) = previous {
+                pen += scaled.kern(previous, info.id);
+            }
+            previous = Some(info.id);
+
+            if info.size[0] > 0. && info.size[1] > 0. {
+                glyphs.push(Glyph {
+                    anchor,
+                    offset: [(pen + info.bearing[0]) * scale, info.bearing[1] * scale],
+                    size: [info.size[0] * scale, info.size[1] * scale],
+                    uv: info.uv,
+                    color: text.color,
+                    rotation: text.rotation,
+                });
+            }
+            pen += info.advance;
+        }
+
+        let width = pen * scale;
+        let (ascent, descent) = (scaled.ascent() * scale, scaled.descent() * scale);
+        let shift = [
+            match text.align[0] {
+                Align::Start => 0.,
+                Align::Center => -0.5 * width,
+                Align::End => -width,
+            },
+            match text.align[1] {
+                Align::Start => -descent,
+                Align::Center => -0.5 * (ascent + descent),
+                Align::End => -ascent,
+            },
+        ];
+
+        for glyph in &mut glyphs {
+            glyph.offset[0] += shift[0];
+            glyph.offset[1] += shift[1];
+        }
+
+        glyphs
+    }
+}
+
+// advance of the string in pixels, without rasterizing anything
+pub(crate) fn text_width(string: &str, size: f32) -> f32 {
+    let font = FontRef::try_from_slice(FONT).expect("invalid bundled font");
+    let scaled = font.as_scaled(PxScale::from(size));
+
+    let mut width = 0.;
+    let mut previous: Option<GlyphId> = None;
+    for c in string.chars() {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            width += scaled.kern(previous, id);
+        }
+        width += scaled.h_advance(id);
+        previous = Some(id);
+    }
+
+    width
+}
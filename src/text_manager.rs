@@ -0,0 +1,153 @@
+use std::{collections::HashMap, sync::Arc};
+
+use vulkano::{buffer::{allocator::{SubbufferAllocator, SubbufferAllocatorCreateInfo}, BufferUsage, Subbuffer}, command_buffer::{allocator::{CommandBufferAllocator, StandardCommandBufferAllocator}, AutoCommandBufferBuilder, PrimaryAutoCommandBuffer}, descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet}, device::{Device, Queue}, format::Format, image::{sampler::{Sampler, SamplerCreateInfo}, view::ImageView}, memory::allocator::{MemoryTypeFilter, StandardMemoryAllocator}, pipeline::{graphics::vertex_input::Vertex, GraphicsPipeline, Pipeline}, render_pass::RenderPass, shader::ShaderModule};
+
+use crate::{camera::CameraSlots, manager::{create_uniform_buffer, create_instance_buffer, create_pipeline, create_vertex_buffer, draw_instances, layer_instances}, circles::MyVertex, instance_buffer::{Changes, InstanceBuffer}, plot::{Layer, Overlay, Plot, PlotId}, text::{self, vs, Glyph, GlyphAtlas}, texture::upload_texture};
+
+pub struct TextManager {
+    device: Arc<Device>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    vs: Arc<ShaderModule>,
+    fs: Arc<ShaderModule>,
+    vertex_buffer: Subbuffer<[MyVertex]>,
+    // texts in data coordinates of each plot, updated in place. glyph sizes are in pixels
+    instances: InstanceBuffer<Glyph>,
+    // titles and labels of each plot, rebuilt when its limits change
+    overlays: HashMap<PlotId, Subbuffer<[Glyph]>>,
+    pipeline: Option<Arc<GraphicsPipeline>>,
+    atlas: GlyphAtlas,
+    atlas_view: Arc<ImageView>,
+    sampler: Arc<Sampler>,
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
+    subbuffer_allocator : SubbufferAllocator
+}
+
+impl TextManager {
+    pub fn new(
+        device: Arc<Device>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        queue: Arc<Queue>,
+        command_buffer_allocator: &StandardCommandBufferAllocator,
+        ) -> Self
+    {
+        let vs = text::vs::load(device.clone()).unwrap();
+        let fs = text::fs::load(device.clone()).unwrap();
+        let descriptor_set_allocator = StandardDescriptorSetAllocator::new(device.clone(), Default::default());
+        let subbuffer_allocator = SubbufferAllocator::new(memory_allocator.clone(), SubbufferAllocatorCreateInfo {
+            buffer_usage: BufferUsage::UNIFORM_BUFFER,
+            memory_type_filter: MemoryTypeFilter::HOST_SEQUENTIAL_WRITE | MemoryTypeFilter::PREFER_DEVICE,
+            ..Default::default()
+        });
+
+        let atlas = GlyphAtlas::new();
+        let atlas_view = upload_texture(
+            memory_allocator.clone(),
+            queue,
+            command_buffer_allocator,
+            Format::R8_UNORM,
+            [atlas.width, atlas.height],
+            atlas.pixels.iter().copied(),
+            );
+        let sampler = Sampler::new(device.clone(), SamplerCreateInfo::simple_repeat_linear_no_mipmap()).unwrap();
+
+        Self {
+            device,
+            vs,
+            fs,
+            pipeline: None,
+            atlas,
+            atlas_view,
+            sampler,
+            vertex_buffer: create_vertex_buffer(memory_allocator.clone()),
+            instances: InstanceBuffer::new(memory_allocator.clone()),
+            memory_allocator,
+            overlays: HashMap::new(),
+            descriptor_set_allocator,
+            subbuffer_allocator
+        }
+    }
+
+    pub fn create_buffers<'a, I>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        plots : I,
+        changes: &mut Changes,
+        ) -> &mut Self
+    where
+        I: IntoIterator<Item = &'a Plot> + Clone,
+    {
+        let ids: Vec<PlotId> = plots.clone().into_iter().map(|plot| plot.id()).collect();
+        self.instances.retain(&ids);
+        self.overlays.retain(|id, _| ids.contains(id));
+        let atlas = &self.atlas;
+        for plot in plots {
+            self.instances.sync(builder, plot.id(), plot.generation(), plot.texts.len(), |start| {
+                plot.scaled_texts(start).iter().flat_map(|t| atlas.layout(t)).collect()
+            }, changes);
+        }
+
+        self
+    }
+
+    pub fn create_overlay_buffers(&mut self, overlays: &[(PlotId, Overlay)]) -> &mut Self {
+        for (id, overlay) in overlays {
+            match create_instance_buffer(self.memory_allocator.clone(), overlay.texts.iter().flat_map(|t| self.atlas.layout(t)).collect()) {
+                Some(buffer) => self.overlays.insert(*id, buffer),
+                None => self.overlays.remove(id),
+            };
+        }
+
+        self
+    }
+
+    pub fn build_pipeline(
+        &mut self,
+        render_pass: Arc<RenderPass>,
+    ) -> &mut Self {
+        // the glyph coverage is blended through the alpha channel
+        self.pipeline = Some(create_pipeline(
+            self.device.clone(),
+            &self.vs,
+            &self.fs,
+            &[MyVertex::per_vertex(), Glyph::per_instance()],
+            render_pass,
+            ));
+        self
+    }
+
+    pub fn draw<A>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer<A>, A>,
+        plot: &Plot,
+        layer: Layer,
+        cameras: &mut CameraSlots,
+    ) where
+        A: CommandBufferAllocator,
+    {
+        // the labels go through the transform of the axes
+        let Some(instances) = layer_instances(&self.instances, &self.overlays, plot.id(), layer) else { return };
+        let transform = plot.transform(layer);
+
+        let pipeline = self.pipeline.clone().unwrap();
+
+        let uniform_buffer = create_uniform_buffer(&self.subbuffer_allocator, plot.id(), cameras, vs::UBO {
+            projection: plot.camera.get_projection().to_cols_array_2d(),
+            modelview: plot.camera.get_view().to_cols_array_2d(),
+            data: transform.to_cols_array_2d(),
+            viewport: plot.extent().map(|v| v as f32),
+        });
+
+        let descriptor_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            pipeline.layout().set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::buffer(0, uniform_buffer),
+                WriteDescriptorSet::image_view_sampler(1, self.atlas_view.clone(), self.sampler.clone()),
+            ],
+            [],
+            )
+            .unwrap();
+        draw_instances(builder, pipeline, descriptor_set, self.vertex_buffer.clone(), instances);
+    }
+}
+
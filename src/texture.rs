@@ -0,0 +1,82 @@
+use std::{error::Error, sync::Arc};
+
+use vulkano::{buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage}, command_buffer::{allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferToImageInfo, PrimaryAutoCommandBuffer}, device::Queue, format::Format, image::{view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage}, memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator}, sync::{self, GpuFuture}};
+
+// copies the texels into a new sampled 2d texture and waits for the transfer
+pub(crate) fn upload_texture<T, I>(
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    queue: Arc<Queue>,
+    command_buffer_allocator: &StandardCommandBufferAllocator,
+    format: Format,
+    extent: [u32; 2],
+    texels: I,
+    ) -> Arc<ImageView>
+where
+    T: BufferContents,
+    I: IntoIterator<Item = T>,
+    I::IntoIter: ExactSizeIterator,
+{
+    let mut builder = AutoCommandBufferBuilder::primary(
+        command_buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+    let view = create_texture(memory_allocator, &mut builder, format, extent, texels).unwrap();
+
+    sync::now(queue.device().clone())
+        .then_execute(queue.clone(), builder.build().unwrap())
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+    view
+}
+
+// a new sampled 2d texture, the copy of the texels is recorded in the builder.
+// fails when the memory runs out or the extent is beyond what the device supports
+pub(crate) fn create_texture<T, I>(
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    format: Format,
+    extent: [u32; 2],
+    texels: I,
+    ) -> Result<Arc<ImageView>, Box<dyn Error>>
+where
+    T: BufferContents,
+    I: IntoIterator<Item = T>,
+    I::IntoIter: ExactSizeIterator,
+{
+    let staging = Buffer::from_iter(
+        memory_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+            ..Default::default()
+        },
+        texels,
+    )?;
+
+    let image = Image::new(
+        memory_allocator,
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format,
+            extent: [extent[0], extent[1], 1],
+            usage: ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    )?;
+
+    builder.copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(staging, image.clone()))?;
+
+    Ok(ImageView::new_default(image)?)
+}
@@ -0,0 +1,115 @@
+// tick positions on 1, 2 or 5 times a power of ten
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ticks {
+    pub major: Vec<f64>,
+    pub minor: Vec<f64>,
+    // distance between two major ticks
+    pub step: f64,
+}
+
+// smallest nice step giving at most `max_ticks` intervals over the range,
+// with the number of minor intervals between two major ticks
+pub fn nice_step(range: f64, max_ticks: usize) -> (f64, usize) {
+    let raw = range / max_ticks.max(1) as f64;
+    let magnitude = 10f64.powf(raw.log10().floor());
+
+    [(1., 5), (2., 4), (5., 5), (10., 5)]
+        .into_iter()
+        .find(|(m, _)| m * magnitude >= raw * (1. - 1e-9))
+        .map(|(m, minor)| (m * magnitude, minor))
+        .unwrap_or((10. * magnitude, 5))
+}
+
+pub fn locate(min: f64, max: f64, max_ticks: usize) -> Ticks {
+    let (min, max) = if min <= max { (min, max) } else { (max, min) };
+    if max - min <= 0. || !(max - min).is_finite() {
+        return Ticks { major: vec![], minor: vec![], step: 0. };
+    }
+
+    let (step, divisions) = nice_step(max - min, max_ticks);
+    let minor_step = step / divisions as f64;
+    // tolerance so a limit sitting on a tick keeps it
+    let eps = step * 1e-9;
+
+    let multiples = |step: f64| {
+        let first = ((min - eps) / step).ceil() as i64;
+        let last = ((max + eps) / step).floor() as i64;
+        (first..=last).map(move |i| i as f64 * step)
+    };
+
+    let major: Vec<f64> = multiples(step).collect();
+    let minor = multiples(minor_step)
+        .filter(|v| major.iter().all(|m| (m - v).abs() > eps))
+        .collect();
+
+    Ticks { major, minor, step }
+}
+
+// enough decimals to tell two ticks apart, scientific notation for very large or small values
+pub fn format(value: f64, step: f64) -> String {
+    if value.abs() < step.abs() * 1e-9 {
+        return "0".to_owned();
+    }
+
+    let exponent = step.abs().log10().floor() as i32;
+    if value.abs() >= 1e5 || step.abs() < 1e-4 {
+        let digits = (value.abs().log10().floor() as i32 - exponent).max(0) as usize;
+        return format!("{value:.digits$e}");
+    }
+
+    let decimals = (-exponent).max(0) as usize;
+    format!("{value:.decimals$}")
+}
+
+impl Ticks {
+    pub fn labels(&self) -> Vec<String> {
+        self.major.iter().map(|v| format(*v, self.step)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nice_steps() {
+        assert_eq!(nice_step(10., 5), (2., 4));
+        assert_eq!(nice_step(10., 10), (1., 5));
+        assert_eq!(nice_step(10., 4), (5., 5));
+        assert_eq!(nice_step(0.3, 5), (0.1, 5));
+        assert_eq!(nice_step(7000., 5), (2000., 4));
+        // a range made of exactly max_ticks steps keeps them
+        assert_eq!(nice_step(1., 5).0, 0.2);
+    }
+
+    #[test]
+    fn locate_major_and_minor() {
+        let ticks = locate(0., 10., 5);
+        assert_eq!(ticks.step, 2.);
+        assert_eq!(ticks.major, vec![0., 2., 4., 6., 8., 10.]);
+        assert_eq!(ticks.minor.len(), 15);
+        assert!(ticks.minor.iter().all(|v| !ticks.major.contains(v)));
+
+        // reversed limits and limits between ticks
+        let ticks = locate(9.5, -0.5, 5);
+        assert_eq!(ticks.major, vec![0., 2., 4., 6., 8.]);
+    }
+
+    #[test]
+    fn locate_degenerate() {
+        for (min, max) in [(1., 1.), (0., f64::INFINITY), (f64::NAN, 1.)] {
+            assert!(locate(min, max, 5).major.is_empty(), "{min} {max}");
+        }
+    }
+
+    #[test]
+    fn label_precision() {
+        let ticks = locate(0., 1., 5);
+        assert_eq!(ticks.labels(), vec!["0", "0.2", "0.4", "0.6", "0.8", "1.0"]);
+
+        assert_eq!(locate(-0.3, 0.3, 6).labels(), vec!["-0.3", "-0.2", "-0.1", "0", "0.1", "0.2", "0.3"]);
+        assert_eq!(locate(0., 5000., 5).labels(), vec!["0", "1000", "2000", "3000", "4000", "5000"]);
+        assert_eq!(format(0.00005, 0.00001), "5e-5");
+        assert_eq!(format(250000., 50000.), "2.5e5");
+    }
+}
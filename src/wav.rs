@@ -0,0 +1,354 @@
+use std::{fs::File, io::{self, BufReader, BufWriter, Read, Write}, path::Path};
+
+use num::complex::Complex;
+
+use crate::fft::{fftfreq, my_fft};
+
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleFormat {
+    Pcm8,
+    Pcm16,
+    Pcm24,
+    Pcm32,
+    Float32,
+    Float64,
+}
+
+impl SampleFormat {
+    fn from_header(format_tag: u16, bits_per_sample: u16) -> io::Result<Self> {
+        match (format_tag, bits_per_sample) {
+            (WAVE_FORMAT_PCM, 8) => Ok(SampleFormat::Pcm8),
+            (WAVE_FORMAT_PCM, 16) => Ok(SampleFormat::Pcm16),
+            (WAVE_FORMAT_PCM, 24) => Ok(SampleFormat::Pcm24),
+            (WAVE_FORMAT_PCM, 32) => Ok(SampleFormat::Pcm32),
+            (WAVE_FORMAT_IEEE_FLOAT, 32) => Ok(SampleFormat::Float32),
+            (WAVE_FORMAT_IEEE_FLOAT, 64) => Ok(SampleFormat::Float64),
+            _ => Err(invalid_data(format!("unsupported wav format {format_tag:#06x} with {bits_per_sample} bits per sample"))),
+        }
+    }
+
+    fn format_tag(&self) -> u16 {
+        match self {
+            SampleFormat::Float32 | SampleFormat::Float64 => WAVE_FORMAT_IEEE_FLOAT,
+            _ => WAVE_FORMAT_PCM,
+        }
+    }
+
+    pub fn bits_per_sample(&self) -> u16 {
+        match self {
+            SampleFormat::Pcm8 => 8,
+            SampleFormat::Pcm16 => 16,
+            SampleFormat::Pcm24 => 24,
+            SampleFormat::Pcm32 | SampleFormat::Float32 => 32,
+            SampleFormat::Float64 => 64,
+        }
+    }
+
+    pub fn bytes_per_sample(&self) -> usize { self.bits_per_sample() as usize / 8 }
+
+    // integer samples are normalized to [-1; 1]
+    fn decode(&self, bytes: &[u8]) -> f64 {
+        match self {
+            SampleFormat::Pcm8 => (bytes[0] as f64 - 128.) / 128.,
+            SampleFormat::Pcm16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64 / 32768.,
+            SampleFormat::Pcm24 => {
+                // sign extend the 24 bits value through the top byte
+                let value = i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8;
+                value as f64 / 8388608.
+            }
+            SampleFormat::Pcm32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64 / 2147483648.,
+            SampleFormat::Float32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            SampleFormat::Float64 => f64::from_le_bytes(bytes[..8].try_into().unwrap()),
+        }
+    }
+
+    // same scale as decode so the written samples read back unchanged, 1 saturates to the largest value
+    fn encode(&self, value: f64, out: &mut Vec<u8>) {
+        let scale = |factor: f64| (value * factor).round().clamp(-factor, factor - 1.);
+        match self {
+            SampleFormat::Pcm8 => out.push((scale(128.) + 128.) as u8),
+            SampleFormat::Pcm16 => out.extend_from_slice(&(scale(32768.) as i16).to_le_bytes()),
+            SampleFormat::Pcm24 => out.extend_from_slice(&(scale(8388608.) as i32).to_le_bytes()[..3]),
+            SampleFormat::Pcm32 => out.extend_from_slice(&(scale(2147483648.) as i32).to_le_bytes()),
+            SampleFormat::Float32 => out.extend_from_slice(&(value as f32).to_le_bytes()),
+            SampleFormat::Float64 => out.extend_from_slice(&value.to_le_bytes()),
+        }
+    }
+}
+
+// de-interleaved audio: channels[c][i] is the i-th sample of channel c
+#[derive(Clone, Debug)]
+pub struct Wav {
+    pub sample_rate: u32,
+    pub format: SampleFormat,
+    pub channels: Vec<Vec<f64>>,
+}
+
+impl Wav {
+    pub fn new(sample_rate: u32, format: SampleFormat, channels: Vec<Vec<f64>>) -> Self {
+        assert!(!channels.is_empty(), "a wav file needs at least one channel");
+        assert!(channels.iter().all(|c| c.len() == channels[0].len()), "all channels must have the same length");
+
+        Self {
+            sample_rate,
+            format,
+            channels,
+        }
+    }
+
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    pub fn from_reader<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut riff = [0u8; 12];
+        reader.read_exact(&mut riff)?;
+        if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+            return Err(invalid_data("not a RIFF/WAVE file"));
+        }
+
+        let mut header = None;
+        loop {
+            let mut chunk = [0u8; 8];
+            reader.read_exact(&mut chunk)?;
+            let size = u32::from_le_bytes(chunk[4..8].try_into().unwrap()) as usize;
+
+            match &chunk[0..4] {
+                b"fmt " => {
+                    let body = read_chunk(&mut reader, size)?;
+                    if body.len() < 16 {
+                        return Err(invalid_data("fmt chunk is too short"));
+                    }
+
+                    let mut format_tag = u16::from_le_bytes([body[0], body[1]]);
+                    let n_channels = u16::from_le_bytes([body[2], body[3]]);
+                    let sample_rate = u32::from_le_bytes(body[4..8].try_into().unwrap());
+                    let bits_per_sample = u16::from_le_bytes([body[14], body[15]]);
+
+                    // the actual format is given by the first two bytes of the sub-format GUID
+                    if format_tag == WAVE_FORMAT_EXTENSIBLE {
+                        if body.len() < 26 {
+                            return Err(invalid_data("extensible fmt chunk is too short"));
+                        }
+                        format_tag = u16::from_le_bytes([body[24], body[25]]);
+                    }
+
+                    if n_channels == 0 {
+                        return Err(invalid_data("wav file has no channel"));
+                    }
+
+                    header = Some((SampleFormat::from_header(format_tag, bits_per_sample)?, n_channels as usize, sample_rate));
+                }
+                b"data" => {
+                    let (format, n_channels, sample_rate) = header.ok_or_else(|| invalid_data("data chunk before fmt chunk"))?;
+                    let body = read_chunk(&mut reader, size)?;
+                    let frame_size = format.bytes_per_sample() * n_channels;
+
+                    let mut channels = vec![Vec::with_capacity(body.len() / frame_size); n_channels];
+                    for frame in body.chunks_exact(frame_size) {
+                        for (channel, sample) in channels.iter_mut().zip(frame.chunks_exact(format.bytes_per_sample())) {
+                            channel.push(format.decode(sample));
+                        }
+                    }
+
+                    return Ok(Self::new(sample_rate, format, channels));
+                }
+                _ => {
+                    read_chunk(&mut reader, size)?;
+                }
+            }
+        }
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.to_writer(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn to_writer<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let n_channels = self.n_channels() as u16;
+        let block_align = n_channels * self.format.bytes_per_sample() as u16;
+
+        let mut data = Vec::with_capacity(block_align as usize * self.len());
+        for i in 0..self.len() {
+            for channel in &self.channels {
+                self.format.encode(channel[i], &mut data);
+            }
+        }
+        let padding = data.len() % 2;
+
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&(4 + 8 + 16 + 8 + data.len() as u32 + padding as u32).to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+
+        writer.write_all(b"fmt ")?;
+        writer.write_all(&16u32.to_le_bytes())?;
+        writer.write_all(&self.format.format_tag().to_le_bytes())?;
+        writer.write_all(&n_channels.to_le_bytes())?;
+        writer.write_all(&self.sample_rate.to_le_bytes())?;
+        writer.write_all(&(self.sample_rate * block_align as u32).to_le_bytes())?;
+        writer.write_all(&block_align.to_le_bytes())?;
+        writer.write_all(&self.format.bits_per_sample().to_le_bytes())?;
+
+        writer.write_all(b"data")?;
+        writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        writer.write_all(&data)?;
+        if padding == 1 {
+            writer.write_all(&[0])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn n_channels(&self) -> usize { self.channels.len() }
+    pub fn len(&self) -> usize { self.channels[0].len() }
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+    pub fn duration(&self) -> f64 { self.len() as f64 / self.sample_rate as f64 }
+    // the `d` argument of fftfreq
+    pub fn sample_spacing(&self) -> f64 { 1. / self.sample_rate as f64 }
+
+    pub fn times(&self) -> Vec<f64> {
+        (0..self.len())
+            .map(|i| i as f64 * self.sample_spacing())
+            .collect()
+    }
+
+    // my_fft only handles power of two sizes, so the channel is zero padded
+    pub fn fft(&self, channel: usize) -> Vec<Complex<f64>> {
+        let n = self.len().next_power_of_two();
+        let values = self.channels[channel]
+            .iter()
+            .map(|v| Complex::new(*v, 0.))
+            .chain(std::iter::repeat(Complex::new(0., 0.)))
+            .take(n)
+            .collect();
+
+        my_fft(&values)
+    }
+
+    // returns (frequencies in Hz, fft) of a channel
+    pub fn spectrum(&self, channel: usize) -> (Vec<f64>, Vec<Complex<f64>>) {
+        let fft = self.fft(channel);
+        (fftfreq(fft.len(), self.sample_spacing()), fft)
+    }
+}
+
+// the size is not trusted for the allocation, a chunk longer than the file fails once its end is reached
+fn read_chunk<R: Read>(reader: &mut R, size: usize) -> io::Result<Vec<u8>> {
+    let mut body = vec![];
+    reader.take(size as u64).read_to_end(&mut body)?;
+    if body.len() < size {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("chunk of {size} bytes ends after {}", body.len())));
+    }
+
+    // chunks are word aligned, some writers omit the padding of the last chunk
+    if size % 2 == 1 {
+        let _ = reader.read(&mut [0u8; 1])?;
+    }
+
+    Ok(body)
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(wav: &Wav) -> Wav {
+        let mut bytes = vec![];
+        wav.to_writer(&mut bytes).unwrap();
+        Wav::from_reader(&bytes[..]).unwrap()
+    }
+
+    #[test]
+    fn round_trip_formats() {
+        // exactly representable by every format
+        let channels = vec![vec![-1., -0.5, 0., 0.25, 0.5], vec![0.5, 0., -0.25, -1., 0.75]];
+        let formats = [
+            SampleFormat::Pcm8,
+            SampleFormat::Pcm16,
+            SampleFormat::Pcm24,
+            SampleFormat::Pcm32,
+            SampleFormat::Float32,
+            SampleFormat::Float64,
+        ];
+
+        for format in formats {
+            let wav = Wav::new(44100, format, channels.clone());
+            let read = round_trip(&wav);
+
+            assert_eq!(read.sample_rate, 44100);
+            assert_eq!(read.format, format);
+            assert_eq!(read.channels, channels, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn pcm_saturates() {
+        for format in [SampleFormat::Pcm8, SampleFormat::Pcm16, SampleFormat::Pcm24, SampleFormat::Pcm32] {
+            let read = round_trip(&Wav::new(8000, format, vec![vec![1., 2., -2.]]));
+            let max = 1. - 1. / (1u64 << (format.bits_per_sample() - 1)) as f64;
+
+            assert_eq!(read.channels[0], vec![max, max, -1.], "{format:?}");
+        }
+    }
+
+    #[test]
+    fn odd_data_is_padded() {
+        let wav = Wav::new(8000, SampleFormat::Pcm8, vec![vec![0., 0.5, -0.5]]);
+        let mut bytes = vec![];
+        wav.to_writer(&mut bytes).unwrap();
+
+        assert_eq!(bytes.len() % 2, 0);
+        assert_eq!(Wav::from_reader(&bytes[..]).unwrap().channels, wav.channels);
+    }
+
+    #[test]
+    fn extensible_header() {
+        let mut bytes = vec![];
+        Wav::new(48000, SampleFormat::Float32, vec![vec![0.5, -0.25]]).to_writer(&mut bytes).unwrap();
+
+        // fmt chunk of 40 bytes with the float sub-format
+        let mut fmt = bytes[20..36].to_vec();
+        fmt[0..2].copy_from_slice(&WAVE_FORMAT_EXTENSIBLE.to_le_bytes());
+        fmt.extend(22u16.to_le_bytes());
+        fmt.extend(32u16.to_le_bytes());
+        fmt.extend(4u32.to_le_bytes());
+        fmt.extend(WAVE_FORMAT_IEEE_FLOAT.to_le_bytes());
+        fmt.extend([0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71]);
+
+        let mut extensible = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+        extensible.extend((fmt.len() as u32).to_le_bytes());
+        extensible.extend(fmt);
+        extensible.extend(&bytes[36..]);
+        let size = extensible.len() as u32 - 8;
+        extensible[4..8].copy_from_slice(&size.to_le_bytes());
+
+        let read = Wav::from_reader(&extensible[..]).unwrap();
+        assert_eq!(read.format, SampleFormat::Float32);
+        assert_eq!(read.sample_rate, 48000);
+        assert_eq!(read.channels, vec![vec![0.5, -0.25]]);
+    }
+
+    #[test]
+    fn truncated_chunk() {
+        let mut bytes = vec![];
+        Wav::new(8000, SampleFormat::Pcm16, vec![vec![0.; 4]]).to_writer(&mut bytes).unwrap();
+        // a data chunk claiming almost 4 GiB
+        let data = bytes.windows(4).position(|w| w == b"data").unwrap();
+        bytes[data + 4..data + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let error = Wav::from_reader(&bytes[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
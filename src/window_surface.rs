@@ -1,7 +1,9 @@
-use std::sync::Arc;
+use std::{error::Error, sync::Arc};
 
-use vulkano::{command_buffer::CommandBufferExecFuture, device::{physical::PhysicalDevice, Device}, image::{view::ImageView, Image, ImageUsage}, instance::Instance, render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass}, swapchain::{self, PresentFuture, Surface, Swapchain, SwapchainAcquireFuture, SwapchainCreateInfo}, sync::{future::{FenceSignalFuture, JoinFuture}, GpuFuture}, Validated, VulkanError};
-use winit::{dpi::PhysicalSize, event_loop::EventLoop, window::{Window, WindowBuilder, WindowId}};
+use vulkano::{command_buffer::CommandBufferExecFuture, device::{physical::PhysicalDevice, Device, DeviceOwned}, format::Format, image::{view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage, SampleCount}, instance::Instance, memory::allocator::{AllocationCreateInfo, StandardMemoryAllocator}, render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass}, swapchain::{self, PresentFuture, Surface, Swapchain, SwapchainAcquireFuture, SwapchainCreateInfo}, sync::{future::{FenceSignalFuture, JoinFuture}, GpuFuture}, Validated, VulkanError};
+use winit::{dpi::PhysicalSize, event_loop::EventLoopWindowTarget, window::{Window, WindowBuilder, WindowId}};
+
+use crate::navigation::Navigation;
 
 pub struct WindowSurface {
     pub surface : Arc<Surface>,
@@ -10,14 +12,23 @@ pub struct WindowSurface {
     pub images : Vec<Arc<Image>>,
     pub render_pass : Arc<RenderPass>,
     pub framebuffers: Vec<Arc<Framebuffer>>,
+    // per framebuffer, images from the rendered one to the swapchain one, each half the size of the previous
+    pub downsampling: Vec<Vec<Arc<Image>>>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    samples: u32,
+    supersampling: u32,
     pub recreate_swapchain : bool,
     pub window_resized : bool,
+    // mouse and keyboard state for panning and zooming
+    pub(crate) navigation: Navigation,
+    // the limits of a plot of the window changed, its overlay needs to be rebuilt
+    pub view_changed : bool,
     pub fences: Vec<Option<Arc<FenceSignalFuture<PresentFuture<CommandBufferExecFuture<JoinFuture<Box<dyn GpuFuture>, SwapchainAcquireFuture>>>>>>>,
     pub previous_fence_i: u32,
 }
 
 impl WindowSurface {
-    pub fn new(instance : Arc<Instance>, device : Arc<Device>, event_loop : &EventLoop<()>) -> Self {
+    pub fn new(instance : Arc<Instance>, device : Arc<Device>, event_loop : &EventLoopWindowTarget<()>) -> Self {
         let window = Arc::new(WindowBuilder::new().build(event_loop).unwrap());
         let surface = Surface::from_window(instance, window.clone()).unwrap();
 
@@ -28,6 +39,8 @@ impl WindowSurface {
                 .expect("failed to get surface capabilities");
 
             let dimension = window.inner_size();
+            // the supersampled frames are blitted into the swapchain images
+            let image_usage = ImageUsage::COLOR_ATTACHMENT | (caps.supported_usage_flags & ImageUsage::TRANSFER_DST);
             let composite_alpha = caps.supported_composite_alpha.into_iter().next().unwrap();
             let image_format = device
                 .physical_device()
@@ -43,7 +56,7 @@ impl WindowSurface {
                     min_image_count: caps.min_image_count,
                     image_format,
                     image_extent: dimension.into(),
-                    image_usage: ImageUsage::COLOR_ATTACHMENT,
+                    image_usage,
                     composite_alpha,
                     ..Default::default()
                 },
@@ -51,8 +64,9 @@ impl WindowSurface {
                 .unwrap()
         };
 
-        let render_pass = get_render_pass(device.clone(), swapchain.clone());
-        let framebuffers = get_framebuffers(&images, render_pass.clone());
+        let memory_allocator = Arc::new(StandardMemoryAllocator::new_default(device.clone()));
+        let render_pass = get_render_pass(device.clone(), swapchain.image_format(), 1);
+        let (framebuffers, downsampling) = get_framebuffers(&images, render_pass.clone(), &memory_allocator, 1, 1);
         let fences = vec![None; images.len()];
 
         Self {
@@ -62,9 +76,15 @@ impl WindowSurface {
             images,
             render_pass,
             framebuffers,
+            downsampling,
+            memory_allocator,
+            samples: 1,
+            supersampling: 1,
             fences,
             recreate_swapchain: false,
             window_resized: false,
+            navigation: Navigation::default(),
+            view_changed: false,
             previous_fence_i: 0
         }
     }
@@ -74,6 +94,13 @@ impl WindowSurface {
     pub fn id(&self) -> WindowId { self.window.id() }
     pub fn frame_in_flight(&self) -> usize { self.images.len() }
 
+    // blocks until the gpu is done with the frames submitted to the window
+    pub fn wait_frames(&self) {
+        for fence in self.fences.iter().flatten() {
+            fence.wait(None).unwrap();
+        }
+    }
+
     pub fn recreate_swapchain(&mut self) {
         let new_dimensions = self.inner_size();
         let (swapchain, images) = self.swapchain
@@ -83,12 +110,37 @@ impl WindowSurface {
                                       })
                                      .expect("failed to recreate swapchain");
 
-        let framebuffers = get_framebuffers(&images, self.render_pass.clone());
+        let (framebuffers, downsampling) = get_framebuffers(&images, self.render_pass.clone(), &self.memory_allocator, self.samples, self.supersampling);
         self.swapchain = swapchain;
+        self.images = images;
         self.framebuffers = framebuffers;
+        self.downsampling = downsampling;
         self.recreate_swapchain = false;
     }
 
+    // samples are clamped to what the device supports, the supersampling factor must be a power of two
+    pub fn set_antialiasing(&mut self, samples: u32, supersampling: u32) {
+        let can_blit = self.swapchain.create_info().image_usage.intersects(ImageUsage::TRANSFER_DST);
+        let samples = supported_samples(self.render_pass.device(), samples);
+        self.samples = samples;
+        self.supersampling = if can_blit { supersampling } else { 1 };
+        self.render_pass = get_render_pass(self.render_pass.device().clone(), self.swapchain.image_format(), samples);
+
+        let (framebuffers, downsampling) = get_framebuffers(&self.images, self.render_pass.clone(), &self.memory_allocator, self.samples, self.supersampling);
+        self.framebuffers = framebuffers;
+        self.downsampling = downsampling;
+    }
+
+    // size of the rendered images, bigger than the window when supersampling
+    pub fn render_extent(&self) -> [u32; 2] {
+        let [width, height]: [u32; 2] = self.inner_size().into();
+        [width * self.supersampling, height * self.supersampling]
+    }
+
+    pub fn supersampling(&self) -> u32 {
+        self.supersampling
+    }
+
     pub fn acquire_next_image(&mut self) -> Result<(u32, bool, SwapchainAcquireFuture), Validated<VulkanError>>  {
         match swapchain::acquire_next_image(self.swapchain.clone(), None).map_err(Validated::unwrap)
         {
@@ -105,38 +157,145 @@ impl WindowSurface {
 
 
 
-fn get_render_pass(device: Arc<Device>, swapchain: Arc<Swapchain>) -> Arc<RenderPass> {
+// the largest sample count the device supports up to the requested one
+pub(crate) fn supported_samples(device: &Device, requested: u32) -> u32 {
+    let properties = device.physical_device().properties();
+    let supported = properties.framebuffer_color_sample_counts;
+
+    [8, 4, 2]
+        .into_iter()
+        .filter(|n| *n <= requested)
+        .find(|n| SampleCount::try_from(*n).is_ok_and(|count| supported.contains_enum(count)))
+        .unwrap_or(1)
+}
+
+// with more than one sample, the multisampled attachment is resolved into the target image
+pub(crate) fn get_render_pass(device: Arc<Device>, format: Format, samples: u32) -> Arc<RenderPass> {
+    if samples <= 1 {
+        return vulkano::single_pass_renderpass!(
+            device,
+            attachments: {
+                color: {
+                    format: format, // set the format the same as the target images
+                    samples: 1,
+                    load_op: Clear,
+                    store_op: Store,
+                },
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {},
+            },
+        )
+        .unwrap();
+    }
+
     vulkano::single_pass_renderpass!(
         device,
         attachments: {
+            multisampled: {
+                format: format,
+                samples: samples,
+                load_op: Clear,
+                store_op: DontCare,
+            },
             color: {
-                format: swapchain.image_format(), // set the format the same as the swapchain
+                format: format,
                 samples: 1,
-                load_op: Clear,
+                load_op: DontCare,
                 store_op: Store,
             },
         },
         pass: {
-            color: [color],
+            color: [multisampled],
+            color_resolve: [color],
             depth_stencil: {},
         },
     )
     .unwrap()
 }
 
-fn get_framebuffers(images: &[Arc<Image>], render_pass: Arc<RenderPass>) -> Vec<Arc<Framebuffer>> {
+// framebuffer rendering into the target image, the first attachment is cleared
+pub(crate) fn create_framebuffer(
+    render_pass: Arc<RenderPass>,
+    memory_allocator: &Arc<StandardMemoryAllocator>,
+    target: Arc<Image>,
+    samples: u32,
+    ) -> Result<Arc<Framebuffer>, Box<dyn Error>>
+{
+    let view = ImageView::new_default(target.clone())?;
+    let attachments = match samples {
+        0 | 1 => vec![view],
+        _ => {
+            let multisampled = Image::new(
+                memory_allocator.clone(),
+                ImageCreateInfo {
+                    image_type: ImageType::Dim2d,
+                    format: target.format(),
+                    extent: target.extent(),
+                    samples: SampleCount::try_from(samples).unwrap(),
+                    usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSIENT_ATTACHMENT,
+                    ..Default::default()
+                },
+                AllocationCreateInfo::default(),
+            )?;
+
+            vec![ImageView::new_default(multisampled)?, view]
+        }
+    };
+
+    Ok(Framebuffer::new(
+        render_pass,
+        FramebufferCreateInfo {
+            attachments,
+            ..Default::default()
+        },
+    )?)
+}
+
+// images of the downsampling chain above the swapchain image, the biggest first
+fn supersampled_images(memory_allocator: &Arc<StandardMemoryAllocator>, image: &Arc<Image>, supersampling: u32) -> Vec<Arc<Image>> {
+    let [width, height, _] = image.extent();
+    let mut factors = vec![];
+    let mut factor = supersampling;
+    while factor > 1 {
+        factors.push(factor);
+        factor /= 2;
+    }
+
+    factors
+        .into_iter()
+        .map(|factor| Image::new(
+            memory_allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: image.format(),
+                extent: [width * factor, height * factor, 1],
+                usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC | ImageUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )
+        .unwrap())
+        .collect()
+}
+
+fn get_framebuffers(
+    images: &[Arc<Image>],
+    render_pass: Arc<RenderPass>,
+    memory_allocator: &Arc<StandardMemoryAllocator>,
+    samples: u32,
+    supersampling: u32,
+    ) -> (Vec<Arc<Framebuffer>>, Vec<Vec<Arc<Image>>>)
+{
     images
         .iter()
         .map(|image| {
-            let view = ImageView::new_default(image.clone()).unwrap();
-            Framebuffer::new(
-                render_pass.clone(),
-                FramebufferCreateInfo {
-                    attachments: vec![view],
-                    ..Default::default()
-                },
-            )
-            .unwrap()
+            let mut chain = supersampled_images(memory_allocator, image, supersampling);
+            chain.push(image.clone());
+
+            let framebuffer = create_framebuffer(render_pass.clone(), memory_allocator, chain[0].clone(), samples).unwrap();
+            (framebuffer, chain)
         })
-        .collect::<Vec<_>>()
+        .unzip()
 }